@@ -1,8 +1,17 @@
 use crate::config;
+use crate::crawler;
+use crate::crawler::Crawler;
 use crate::get;
-use crate::influx;
+use crate::get::{Fetcher, Response, Timings};
 use crate::influx::translate;
-use influxdb::Error as InfluxError;
+use crate::scheduler::Scheduler;
+use crate::sink;
+use crate::sink::{Sink, SinkError};
+use crate::source;
+use futures::stream::{self, StreamExt};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
 
 // AppError
 // Define AppError
@@ -10,9 +19,11 @@ use influxdb::Error as InfluxError;
 pub enum AppError {
     GetError(get::GetError),
     Str(String),
-    InfluxError(InfluxError),
+    SinkError(SinkError),
     ConfigError(config::ConfigError),
     TranslateError(translate::TranslateError),
+    IOError(std::io::Error),
+    ClapError(clap::Error),
 }
 
 // GetError
@@ -29,14 +40,14 @@ impl From<String> for AppError {
     }
 }
 
-// InfluxError
-impl From<InfluxError> for AppError {
-    fn from(err: InfluxError) -> AppError {
-        AppError::InfluxError(err)
+// SinkError
+impl From<SinkError> for AppError {
+    fn from(err: SinkError) -> AppError {
+        AppError::SinkError(err)
     }
 }
 
-// InfluxError
+// ConfigError
 impl From<config::ConfigError> for AppError {
     fn from(err: config::ConfigError) -> AppError {
         AppError::ConfigError(err)
@@ -50,36 +61,260 @@ impl From<translate::TranslateError> for AppError {
     }
 }
 
-pub fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
-    // get configs info by walking inside conf.d directory
-    let configs = config::get_configs_files(matches.value_of("conf.d").unwrap())?;
+// IOError
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> AppError {
+        AppError::IOError(err)
+    }
+}
 
-    // ensure conn to influx
-    let client = influx::push::create_influx_client(
-        // unwraping is ok here since defaults value are set
-        matches.value_of("influx_user").unwrap(),
-        matches.value_of("influx_password").unwrap(),
-        matches.value_of("influx_database").unwrap(),
-        matches.value_of("influx_host").unwrap(),
-    );
+// ClapError
+impl From<clap::Error> for AppError {
+    fn from(err: clap::Error) -> AppError {
+        AppError::ClapError(err)
+    }
+}
 
-    for conf in configs {
-        // analysing conf
-        debug!("Analysing conf {} of kind {}", &conf.name, &conf.kind);
-
-        match get::get_data(&conf) {
-            Ok(data) => {
-                // translate data
-                let measurement = influx::translate::new_from(&data, &conf)?;
-                // push data to influxdb
-                influx::push::push_measurement(&client, measurement)?;
-            }
+// Functions - private
+// collect fetches data for a config, translates it and sends it to all sinks
+async fn collect(
+    fetcher: &Fetcher,
+    sinks: &[Box<dyn Sink>],
+    conf: &config::Config,
+    timings: bool,
+) -> Result<(), AppError> {
+    // analysing conf
+    debug!("Analysing conf {} of kind {}", &conf.name, &conf.kind);
+
+    // find the source handling this kind
+    let source = match source::find(&conf.kind) {
+        Some(source) => source,
+        None => {
+            return Err(AppError::ConfigError(
+                config::ConfigError::NotSupportedError,
+            ))
+        }
+    };
+
+    // responses of the source requests give availability, written whatever happens next
+    let traced = fetcher.traced();
+    let network = async {
+        if timings {
+            fetcher.timings(&conf.url).await
+        } else {
+            Timings::default()
+        }
+    };
+    let (network, fetched) = futures::join!(network, source.fetch(&traced, conf));
+
+    let mut res = Ok(());
+    let mut measurements = match &fetched {
+        // translate data
+        Ok(data) => match source.translate(data, conf) {
+            Ok(measurements) => measurements,
             Err(e) => {
+                res = Err(AppError::from(e));
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            error!("{:?}", e);
+            warn!("Error getting data for config {}", conf.name);
+            Vec::new()
+        }
+    };
+    measurements.push(new_from_availability(
+        &traced.responses(),
+        &network,
+        &fetched,
+        conf,
+    ));
+
+    // push data to all sinks, an error on one sink does not stop the others
+    for sink in sinks {
+        if let Err(e) = sink.push(conf, &measurements).await {
+            warn!("Error pushing config {} to sink {}", conf.name, sink.name());
+            res = Err(AppError::from(e));
+        }
+    }
+
+    res
+}
+
+// new_from_availability converts responses of a collection into a Measurement,
+// up if data was fetched, which needs a success status on each request
+fn new_from_availability(
+    responses: &[Response],
+    network: &Timings,
+    fetched: &Result<Vec<serde_json::Value>, get::GetError>,
+    conf: &config::Config,
+) -> translate::Measurement {
+    let mut measurement = translate::Measurement::new(conf);
+    measurement.key = "availability".to_string();
+    measurement
+        .tags
+        .insert("kind".to_string(), conf.kind.clone());
+
+    // status of the failed request, or of the last one
+    let status = match fetched {
+        Err(get::GetError::StatusError(status)) => Some(*status),
+        _ => responses.last().map(|r| r.status),
+    };
+    let elapsed = if responses.is_empty() {
+        None
+    } else {
+        Some(responses.iter().map(|r| r.elapsed).sum())
+    };
+
+    let ms = |d: Option<Duration>| d.map(|d| d.as_millis() as i64);
+
+    // add fields
+    measurement.add_opt_int("up", Some(fetched.is_ok() as i64));
+    measurement.add_opt_int("status", status.map(i64::from));
+    measurement.add_opt_int("requests", Some(responses.len() as i64));
+    measurement.add_opt_int("response_time_ms", ms(elapsed));
+    measurement.add_opt_int("dns_time_ms", ms(network.dns));
+    measurement.add_opt_int("connect_time_ms", ms(network.connect));
+
+    measurement
+}
+
+// collect_all collects configs concurrently, with at most parallel configs at a time
+async fn collect_all(
+    fetcher: &Fetcher,
+    sinks: &[Box<dyn Sink>],
+    configs: Vec<&config::Config>,
+    parallel: usize,
+    timings: bool,
+) -> Result<(), AppError> {
+    let results: Vec<Result<(), AppError>> = stream::iter(configs)
+        .map(|conf| async move {
+            let res = collect(fetcher, sinks, conf, timings).await;
+            if let Err(e) = &res {
                 error!("{:?}", e);
-                warn!("Error getting data for config {}", conf.name);
+                warn!("Error collecting config {}", conf.name);
             }
+            res
+        })
+        .buffer_unordered(parallel)
+        .collect()
+        .await;
+
+    // all configs are collected even on error, but the first one is returned
+    results.into_iter().collect()
+}
+
+// daemon keeps collecting configs, each one at its own interval
+fn daemon(
+    rt: &mut Runtime,
+    fetcher: &Fetcher,
+    sinks: &[Box<dyn Sink>],
+    configs: &[config::Config],
+    interval: Duration,
+    parallel: usize,
+    timings: bool,
+) -> Result<(), AppError> {
+    // use config interval if set, default one if not
+    let mut intervals = Vec::new();
+    for conf in configs {
+        intervals.push(conf.get_interval()?.unwrap_or(interval));
+    }
+
+    let mut scheduler = Scheduler::new(intervals, Instant::now());
+
+    info!("Starting daemon mode, default interval is {:?}", interval);
+
+    loop {
+        let due = scheduler
+            .due(Instant::now())
+            .into_iter()
+            .map(|index| &configs[index])
+            .collect();
+
+        // errors are already logged, do not stop the daemon, just wait for next run
+        let _ = rt.block_on(collect_all(fetcher, sinks, due, parallel, timings));
+
+        // sleep until next config is due
+        if let Some(next) = scheduler.next_wakeup() {
+            let now = Instant::now();
+            if next > now {
+                debug!("Sleeping for {:?}", next - now);
+                thread::sleep(next - now);
+            }
+        }
+    }
+}
+
+// Functions - public
+pub fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
+    let crawl = matches.is_present("crawl_seeds");
+
+    // get configs info by walking inside conf.d directory
+    let mut configs = match config::get_configs_files(matches.value_of("conf.d").unwrap()) {
+        Ok(configs) => configs,
+        // in crawl mode, configs can come from the crawler only
+        Err(e) if crawl => {
+            warn!("No config used from conf.d: {:?}", e);
+            Vec::new()
+        }
+        Err(e) => return Err(AppError::from(e)),
+    };
+
+    // concurrency limits
+    let parallel = value_t!(matches, "parallel", usize)?;
+    let per_host = value_t!(matches, "per_host", usize)?;
+    let timeout = config::parse_interval(matches.value_of("timeout").unwrap())?;
+    let timings = matches.is_present("network_timings");
+
+    // nothing would ever be collected with a zero limit
+    if parallel < 1 || per_host < 1 {
+        return Err(AppError::from(
+            "parallel and per-host must be at least 1".to_string(),
+        ));
+    }
+
+    // one runtime and one http client shared by all configs
+    let mut rt = Runtime::new()?;
+    let fetcher = Fetcher::new(per_host, timeout)?;
+
+    // add instances found by the crawler, hand written configs take precedence
+    if crawl {
+        let crawler = Crawler::new(&matches)?;
+        for conf in rt.block_on(crawler.crawl(&fetcher)) {
+            if !configs.iter().any(|c| crawler::is_same_instance(c, &conf)) {
+                configs.push(conf);
+            }
+        }
+
+        if configs.is_empty() {
+            return Err(AppError::from(config::ConfigError::NoConfigError));
         }
     }
 
-    Ok(())
+    // create all sinks, inside the runtime since some of them spawn tasks
+    let sinks = rt.enter(|| {
+        matches
+            .values_of("sinks")
+            // unwraping is ok here since default value is set
+            .unwrap()
+            .map(|name| sink::new(name, &matches, timeout))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    // in daemon mode, run forever
+    if matches.is_present("daemon") {
+        let interval = config::parse_interval(matches.value_of("interval").unwrap())?;
+        return daemon(
+            &mut rt, &fetcher, &sinks, &configs, interval, parallel, timings,
+        );
+    }
+
+    // or just once
+    rt.block_on(collect_all(
+        &fetcher,
+        &sinks,
+        configs.iter().collect(),
+        parallel,
+        timings,
+    ))
 }
@@ -1,20 +1,12 @@
 // Mod config - used to parse config files
 // Uses
+use crate::source;
 use serde::Deserialize;
-use std::{fmt, fs, io, vec::Vec};
+use std::collections::HashMap;
+use std::time::Duration;
+use std::{env, fmt, fs, io, vec::Vec};
 use url::Url;
 
-// Const
-// array of supported kinds
-pub const SUPPORTED: [&str; 6] = [
-    "mastodon",
-    "mastodon_user",
-    "pleroma",
-    "pleroma_user",
-    "plume",
-    "funkwhale",
-];
-
 // Errors
 // Define NotSupportedError
 #[derive(Debug, Clone)]
@@ -54,8 +46,14 @@ pub enum ConfigError {
     IOError(std::io::Error),
     TomlError(toml::de::Error),
     NotSupportedError,
+    MissingOptionError(String),
+    InvalidOptionError(String),
     UrlError(url::ParseError),
     NoConfigError,
+    IntervalError(humantime::DurationError),
+    ZeroIntervalError,
+    EnvError(env::VarError),
+    HeaderError(String),
 }
 
 // implement from
@@ -80,11 +78,74 @@ impl From<url::ParseError> for ConfigError {
     }
 }
 
+// IntervalError
+impl From<humantime::DurationError> for ConfigError {
+    fn from(err: humantime::DurationError) -> ConfigError {
+        ConfigError::IntervalError(err)
+    }
+}
+
+// EnvError
+impl From<env::VarError> for ConfigError {
+    fn from(err: env::VarError) -> ConfigError {
+        ConfigError::EnvError(err)
+    }
+}
+
 // Structs - public
+// FieldType is the type of a field declared in a mapping
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Int,
+    Float,
+    String,
+}
+
+// Mapping declares how to get a field from a json document
+#[derive(Deserialize, Debug, Clone)]
+pub struct Mapping {
+    // name of the field
+    pub name: String,
+    // JSON pointer (eg: "/stats/user_count") or dotted path (eg: "stats.user_count")
+    pub path: String,
+    // type of the field
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+}
+
 // Options struct found in config files
 #[derive(Deserialize, Debug, Clone)]
 pub struct Options {
     user_id: Option<String>,
+    // account handle, resolved into a user id (eg: "@papey@rage.love")
+    account: Option<String>,
+    // file used to keep the resolved account id between runs
+    account_file: Option<String>,
+    // number of recent statuses used to compute engagement (user and hashtag kinds)
+    recent_statuses: Option<u32>,
+    // name of the community (lemmy_community kind)
+    community: Option<String>,
+    // hashtags followed, without the leading # (mastodon_hashtag kind)
+    tags: Option<Vec<String>>,
+    // handle of the video channel (peertube_channel kind)
+    channel: Option<String>,
+    // file used to keep known peers between runs (peers kinds)
+    peers_file: Option<String>,
+    // access token sent as a bearer token with every request
+    token: Option<String>,
+    // name of the env var holding the access token
+    token_env: Option<String>,
+    // path of the file holding the access token
+    token_file: Option<String>,
+    // extra headers sent with every request (eg: "User-Agent")
+    headers: Option<HashMap<String, String>>,
+    // path of the endpoint (json kind)
+    endpoint: Option<String>,
+    // measurement key (json kind)
+    key: Option<String>,
+    // fields mappings (json kind)
+    fields: Option<Vec<Mapping>>,
 }
 
 // Struct Config represent data read from conf.d files
@@ -96,25 +157,135 @@ pub struct Config {
     pub url: String,
     // kind
     pub kind: String,
+    // interval between two runs in daemon mode (eg: "5m")
+    pub interval: Option<String>,
     // options
     pub options: Option<Options>,
 }
 
 // Implement new method for config
 impl Config {
-    #[allow(dead_code)]
-    pub fn new(name: String, url: String, kind: String, _options: Option<Options>) -> Config {
+    pub fn new(name: String, url: String, kind: String, options: Option<Options>) -> Config {
         Config {
             name,
             url,
             kind,
-            options: None,
+            interval: None,
+            options,
         }
     }
 
     pub fn get_user_id(&self) -> Option<String> {
         self.options.as_ref().and_then(|o| o.user_id.clone())
     }
+
+    pub fn get_account(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.account.clone())
+    }
+
+    pub fn get_account_file(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.account_file.clone())
+    }
+
+    pub fn get_recent_statuses(&self) -> Option<u32> {
+        self.options.as_ref().and_then(|o| o.recent_statuses)
+    }
+
+    pub fn get_community(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.community.clone())
+    }
+
+    pub fn get_tags(&self) -> Option<&Vec<String>> {
+        self.options.as_ref().and_then(|o| o.tags.as_ref())
+    }
+
+    pub fn get_channel(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.channel.clone())
+    }
+
+    pub fn get_peers_file(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.peers_file.clone())
+    }
+
+    // get_token returns the access token, set inline, in an env var or in a file
+    pub fn get_token(&self) -> Result<Option<String>, ConfigError> {
+        let options = match &self.options {
+            Some(o) => o,
+            None => return Ok(None),
+        };
+
+        if let Some(token) = &options.token {
+            return Ok(Some(token.clone()));
+        }
+
+        if let Some(var) = &options.token_env {
+            return Ok(Some(env::var(var)?.trim().to_string()));
+        }
+
+        if let Some(path) = &options.token_file {
+            return Ok(Some(fs::read_to_string(path)?.trim().to_string()));
+        }
+
+        Ok(None)
+    }
+
+    // get_headers returns headers sent with every request, including the access token
+    pub fn get_headers(&self) -> Result<Vec<(String, String)>, ConfigError> {
+        let mut headers = Vec::new();
+
+        if let Some(token) = self.get_token()? {
+            headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+        }
+
+        if let Some(extra) = self.options.as_ref().and_then(|o| o.headers.as_ref()) {
+            for (name, value) in extra {
+                headers.push((name.clone(), value.clone()));
+            }
+        }
+
+        // ensure headers can be sent, before any request
+        for (name, value) in &headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                || reqwest::header::HeaderValue::from_str(value).is_err()
+            {
+                return Err(ConfigError::HeaderError(name.clone()));
+            }
+        }
+
+        Ok(headers)
+    }
+
+    // get_headers_for returns headers sent with a request to url, the access token and
+    // extra headers are only sent to the instance of the config, never to another server
+    pub fn get_headers_for(&self, url: &str) -> Result<Vec<(String, String)>, ConfigError> {
+        let headers = self.get_headers()?;
+        let origin = |u: &str| Url::parse(u).ok().map(|u| u.origin());
+
+        match (origin(&self.url), origin(url)) {
+            (Some(a), Some(b)) if a.is_tuple() && a == b => Ok(headers),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    pub fn get_endpoint(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.endpoint.clone())
+    }
+
+    pub fn get_key(&self) -> Option<String> {
+        self.options.as_ref().and_then(|o| o.key.clone())
+    }
+
+    pub fn get_fields(&self) -> Option<&Vec<Mapping>> {
+        self.options.as_ref().and_then(|o| o.fields.as_ref())
+    }
+
+    // get_interval returns the interval set in config, if any
+    pub fn get_interval(&self) -> Result<Option<Duration>, ConfigError> {
+        match &self.interval {
+            Some(i) => Ok(Some(parse_interval(i)?)),
+            None => Ok(None),
+        }
+    }
 }
 
 // Implement Display trait for config
@@ -126,10 +297,11 @@ impl fmt::Display for Config {
             self.name, self.url, self.kind
         )?;
 
-        // if there is an user id, print it
-        match self.get_user_id() {
-            Some(i) => write!(f, ", options [user_id: {}]", i),
-            None => write!(f, ""),
+        // if there is an user id or an account, print it
+        match (self.get_user_id(), self.get_account()) {
+            (Some(i), _) => write!(f, ", options [user_id: {}]", i),
+            (None, Some(a)) => write!(f, ", options [account: {}]", a),
+            (None, None) => write!(f, ""),
         }
     }
 }
@@ -143,24 +315,40 @@ fn read_config_file(path: &std::path::PathBuf) -> Result<Config, ConfigError> {
     // try to read content
     let content = fs::read_to_string(&path)?;
 
-    // debug file content
-    debug!("Content of file {}, is : \n{}", &path.display(), &content);
-
     // try desirializing toml file into config struct
     let config: Config = toml::from_str(&content)?;
 
-    // debug struct deserializing
+    // debug struct deserializing, never the file content since it can hold a token
     debug!("{}", &config);
 
+    // check if interval is valid
+    if let Err(e) = config.get_interval() {
+        error!("interval of config {} is not valid", &config.name);
+        return Err(e);
+    }
+
+    // check if token and headers can be used
+    if let Err(e) = config.get_headers() {
+        error!("token or headers of config {} are not valid", &config.name);
+        return Err(e);
+    }
+
     // check if kind is supported
-    if SUPPORTED.contains(&config.kind.as_str()) {
+    if let Some(source) = source::find(&config.kind) {
+        // check if config has everything needed by source
+        source.validate(&config)?;
+
         match verify_url(&config) {
             Ok(_) => return Ok(config),
             Err(e) => return Err(e),
         }
     } else {
         // or error
-        error!("{} kind of config not supported, moving on", &config.kind);
+        error!(
+            "{} kind of config not supported, moving on (supported kinds: {})",
+            &config.kind,
+            source::supported().join(", ")
+        );
         Err(ConfigError::NotSupportedError)
     }
 }
@@ -177,6 +365,18 @@ fn verify_url(conf: &Config) -> Result<(), ConfigError> {
 }
 
 // Functions - public
+// Parse a human readable interval (eg: "5m", "1h 30m") into a duration
+pub fn parse_interval(interval: &str) -> Result<Duration, ConfigError> {
+    let duration = humantime::parse_duration(interval)?;
+
+    // a zero interval would make the daemon spin
+    if duration.as_secs() == 0 {
+        return Err(ConfigError::ZeroIntervalError);
+    }
+
+    Ok(duration)
+}
+
 // Create a default test config (used in tests)
 #[allow(dead_code)]
 pub fn create_test_config() -> Config {
@@ -243,4 +443,97 @@ mod tests {
             Err(_) => panic!("verify_url test should failed... :shrug:"),
         }
     }
+
+    #[test]
+    fn test_get_interval() {
+        // prepare
+        let mut config = create_test_config();
+
+        // no interval set
+        assert_eq!(config.get_interval().unwrap(), None);
+
+        config.interval = Some("5m".to_string());
+
+        // assert
+        assert_eq!(
+            config.get_interval().unwrap(),
+            Some(Duration::from_secs(300))
+        );
+    }
+
+    #[test]
+    fn test_get_headers() {
+        // prepare
+        let path = env::temp_dir().join(format!("fediwatcher.token.{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        env::set_var("FEDIWATCHER_TEST_TOKEN", "from-env");
+
+        let parse = |options: &str| -> Config {
+            toml::from_str(&format!(
+                r#"
+                name = "rage.love"
+                url = "https://rage.love"
+                kind = "mastodon"
+
+                [options]
+                {}
+                "#,
+                options
+            ))
+            .expect("Error parsing test config")
+        };
+
+        // launch test
+        // no options, no headers
+        assert!(create_test_config().get_headers().unwrap().is_empty());
+
+        let conf = parse(
+            r#"token = "inline"
+            [options.headers]
+            User-Agent = "fediwatcher""#,
+        );
+        let headers = conf.get_headers().unwrap();
+        let auth = ("Authorization".to_string(), "Bearer inline".to_string());
+        let agent = ("User-Agent".to_string(), "fediwatcher".to_string());
+        assert!(headers.contains(&auth));
+        assert!(headers.contains(&agent));
+
+        // token never leaves the instance
+        let headers = conf
+            .get_headers_for("https://rage.love/users/papey")
+            .unwrap();
+        assert!(headers.contains(&auth));
+        assert!(conf
+            .get_headers_for("https://evil.social/users/papey")
+            .unwrap()
+            .is_empty());
+        assert!(conf
+            .get_headers_for("http://rage.love/users/papey")
+            .unwrap()
+            .is_empty());
+
+        let conf = parse(r#"token_env = "FEDIWATCHER_TEST_TOKEN""#);
+        assert_eq!(conf.get_token().unwrap(), Some("from-env".to_string()));
+
+        let conf = parse(&format!("token_file = {:?}", path.to_str().unwrap()));
+        assert_eq!(conf.get_token().unwrap(), Some("from-file".to_string()));
+
+        let conf = parse(r#"token_env = "FEDIWATCHER_TEST_NOPE""#);
+        assert!(conf.get_headers().is_err());
+
+        let conf = parse(
+            r#"[options.headers]
+            "bad header" = "ohno""#,
+        );
+        assert!(conf.get_headers().is_err());
+
+        // cleanup
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_interval_nok() {
+        assert!(parse_interval("ohno").is_err());
+        assert!(parse_interval("0s").is_err());
+    }
 }
@@ -0,0 +1,392 @@
+// Mod crawler - used to discover instances by following peers from seed instances
+// Uses
+use crate::config::Config;
+use crate::get::{Fetcher, GetError};
+use crate::source::nodeinfo;
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+use url::Url;
+
+// Statics
+// kind of config associated to a software name found in NodeInfo
+static KINDS: [(&str, &str); 20] = [
+    ("mastodon", "mastodon"),
+    ("pleroma", "pleroma"),
+    ("akkoma", "akkoma"),
+    ("gotosocial", "gotosocial"),
+    ("misskey", "misskey"),
+    ("firefish", "misskey"),
+    ("sharkey", "misskey"),
+    ("calckey", "misskey"),
+    ("foundkey", "misskey"),
+    ("lemmy", "lemmy"),
+    ("peertube", "peertube"),
+    ("pixelfed", "pixelfed"),
+    ("friendica", "friendica"),
+    ("hubzilla", "hubzilla"),
+    ("diaspora", "diaspora"),
+    ("bookwyrm", "bookwyrm"),
+    ("writefreely", "writefreely"),
+    ("mobilizon", "mobilizon"),
+    ("funkwhale", "funkwhale"),
+    ("plume", "plume"),
+];
+
+// kinds exposing their peers on /api/v1/instance/peers
+static PEERS_KINDS: [&str; 4] = ["mastodon", "pleroma", "akkoma", "gotosocial"];
+
+// max time spent probing one instance, dead instances are common
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Structs - private
+// Probe is what is learned about an instance
+struct Probe {
+    config: Config,
+    peers: Vec<String>,
+}
+
+// Structs - public
+// Crawler holds crawl settings
+pub struct Crawler {
+    // domains to start from
+    seeds: Vec<String>,
+    // max number of hops from seeds
+    depth: usize,
+    // max number of instances discovered
+    max: usize,
+    // domains never crawled, subdomains included
+    deny: Vec<String>,
+    // max number of instances probed concurrently
+    parallel: usize,
+}
+
+// Implements for Crawler
+impl Crawler {
+    // new creates a crawler from command line args
+    pub fn new(matches: &clap::ArgMatches) -> Result<Crawler, clap::Error> {
+        let seeds = matches
+            .values_of("crawl_seeds")
+            .map(|seeds| seeds.filter_map(domain_of).collect())
+            .unwrap_or_default();
+        let deny = matches
+            .values_of("crawl_deny")
+            .map(|deny| deny.filter_map(domain_of).collect())
+            .unwrap_or_default();
+
+        Ok(Crawler {
+            seeds,
+            depth: value_t!(matches, "crawl_depth", usize)?,
+            max: value_t!(matches, "crawl_max", usize)?,
+            deny,
+            parallel: value_t!(matches, "parallel", usize)?,
+        })
+    }
+
+    // is_denied checks if domain, or one of its parents, is in deny list
+    fn is_denied(&self, domain: &str) -> bool {
+        self.deny
+            .iter()
+            .any(|d| domain == d || domain.ends_with(&format!(".{}", d)))
+    }
+
+    // crawl discovers instances, level by level, and returns a config for each of them
+    pub async fn crawl(&self, fetcher: &Fetcher) -> Vec<Config> {
+        self.crawl_with(|domain, follow| probe(fetcher, domain, follow))
+            .await
+    }
+
+    // crawl_with discovers instances using probe to learn about each domain
+    async fn crawl_with<F, Fut>(&self, probe: F) -> Vec<Config>
+    where
+        F: Fn(String, bool) -> Fut,
+        Fut: Future<Output = Option<Probe>>,
+    {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut configs = Vec::new();
+
+        let mut level: Vec<String> = self
+            .seeds
+            .iter()
+            .filter(|s| !self.is_denied(s))
+            .cloned()
+            .collect();
+        seen.extend(level.iter().cloned());
+
+        for depth in 0..=self.depth {
+            info!("Crawling {} instances at depth {}", level.len(), depth);
+
+            // peers of the last level are not needed
+            let follow = depth < self.depth;
+
+            let mut probes = stream::iter(level)
+                .map(|domain| probe(domain, follow))
+                .buffer_unordered(self.parallel);
+
+            let mut next = Vec::new();
+            // stop probing as soon as enough instances are found
+            while configs.len() < self.max {
+                let probe = match probes.next().await {
+                    Some(Some(probe)) => probe,
+                    Some(None) => continue,
+                    None => break,
+                };
+
+                for peer in probe.peers {
+                    if !self.is_denied(&peer) && seen.insert(peer.clone()) {
+                        next.push(peer);
+                    }
+                }
+
+                configs.push(probe.config);
+            }
+
+            if configs.len() >= self.max || next.is_empty() {
+                break;
+            }
+
+            level = next;
+        }
+
+        info!("Crawler found {} instances", configs.len());
+
+        configs
+    }
+}
+
+// Functions - public
+// is_same_instance checks if two configs collect the same kind on the same domain,
+// whatever the way their urls are written (eg: "https://Rage.Love/" and "https://rage.love")
+pub fn is_same_instance(a: &Config, b: &Config) -> bool {
+    a.kind == b.kind
+        && match (domain_of(&a.url), domain_of(&b.url)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.url == b.url,
+        }
+}
+
+// Functions - private
+// domain_of extracts the domain of an url or a domain (eg: "https://rage.love/" or "rage.love")
+fn domain_of(s: &str) -> Option<String> {
+    let s = s.trim();
+    let url = if s.contains("://") {
+        Url::parse(s)
+    } else {
+        Url::parse(&format!("https://{}", s))
+    };
+
+    url.ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+}
+
+// kind_of returns the kind of config used to collect software
+fn kind_of(software: &str) -> &'static str {
+    let software = software.to_lowercase();
+
+    KINDS
+        .iter()
+        .find(|(name, _)| *name == software)
+        .map(|(_, kind)| *kind)
+        // any other software speaking NodeInfo
+        .unwrap_or("nodeinfo")
+}
+
+// discover finds the kind of an instance and, if follow is set, its peers
+async fn discover(
+    fetcher: &Fetcher,
+    url: &str,
+    follow: bool,
+) -> Result<(&'static str, Vec<String>), GetError> {
+    let well_known = fetcher
+        .get_json(&format!("{}{}", url, "/.well-known/nodeinfo"), &[])
+        .await?;
+    let href = nodeinfo::find_href(&well_known).ok_or(GetError::DiscoveryError)?;
+    let doc = fetcher.get_json(&href, &[]).await?;
+
+    let kind = kind_of(doc["software"]["name"].as_str().unwrap_or_default());
+
+    let mut peers = Vec::new();
+    if follow && PEERS_KINDS.contains(&kind) {
+        // some instances hide their peers, this is not an error
+        if let Ok(list) = fetcher
+            .get_json(&format!("{}{}", url, "/api/v1/instance/peers"), &[])
+            .await
+        {
+            peers = list
+                .as_array()
+                .map(|l| {
+                    l.iter()
+                        .filter_map(|p| p.as_str())
+                        .filter_map(domain_of)
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+    }
+
+    Ok((kind, peers))
+}
+
+// probe tries to discover domain, None if the instance can not be reached
+async fn probe(fetcher: &Fetcher, domain: String, follow: bool) -> Option<Probe> {
+    let url = format!("https://{}", domain);
+
+    match tokio::time::timeout(PROBE_TIMEOUT, discover(fetcher, &url, follow)).await {
+        Ok(Ok((kind, peers))) => {
+            debug!(
+                "Found {} instance {}, with {} peers",
+                kind,
+                domain,
+                peers.len()
+            );
+            Some(Probe {
+                config: Config::new(domain, url, kind.to_string(), None),
+                peers,
+            })
+        }
+        Ok(Err(e)) => {
+            debug!("Error probing {} : {:?}", domain, e);
+            None
+        }
+        Err(_) => {
+            debug!("Timeout probing {}", domain);
+            None
+        }
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source;
+
+    #[test]
+    fn test_kind_of() {
+        assert_eq!(kind_of("Mastodon"), "mastodon");
+        assert_eq!(kind_of("sharkey"), "misskey");
+        assert_eq!(kind_of("shit"), "nodeinfo");
+
+        // all kinds must be supported
+        for (_, kind) in KINDS.iter() {
+            assert!(source::find(kind).is_some(), "{} is not supported", kind);
+        }
+    }
+
+    #[test]
+    fn test_domain_of() {
+        assert_eq!(domain_of("rage.love").unwrap(), "rage.love");
+        assert_eq!(domain_of("https://Rage.Love/").unwrap(), "rage.love");
+        assert!(domain_of("not a domain").is_none());
+    }
+
+    #[test]
+    fn test_is_same_instance() {
+        // prepare
+        let conf = |url: &str, kind: &str| {
+            Config::new(
+                "rage.love".to_string(),
+                url.to_string(),
+                kind.to_string(),
+                None,
+            )
+        };
+
+        // launch test
+        assert!(is_same_instance(
+            &conf("https://rage.love", "mastodon"),
+            &conf("https://Rage.Love/", "mastodon")
+        ));
+        assert!(!is_same_instance(
+            &conf("https://rage.love", "mastodon"),
+            &conf("https://rage.love", "mastodon_user")
+        ));
+        assert!(!is_same_instance(
+            &conf("https://rage.love", "mastodon"),
+            &conf("https://mastodon.social", "mastodon")
+        ));
+    }
+
+    // fake_probe answers like an instance of network, where each domain has a list of peers
+    async fn fake_probe(
+        network: &[(&str, Vec<&str>)],
+        domain: String,
+        follow: bool,
+    ) -> Option<Probe> {
+        let (_, peers) = network.iter().find(|(d, _)| *d == domain)?;
+
+        Some(Probe {
+            config: Config::new(
+                domain.clone(),
+                format!("https://{}", domain),
+                "mastodon".to_string(),
+                None,
+            ),
+            peers: if follow {
+                peers.iter().map(|p| p.to_string()).collect()
+            } else {
+                vec![]
+            },
+        })
+    }
+
+    #[test]
+    fn test_crawl() {
+        // prepare
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        // b is unreachable, d is only reachable at depth 2
+        let network = vec![
+            ("a.social", vec!["c.social", "bad.social", "a.social"]),
+            ("c.social", vec!["d.social", "a.social"]),
+            ("d.social", vec!["e.social"]),
+            ("bad.social", vec![]),
+        ];
+        let crawler = |depth: usize, max: usize| Crawler {
+            seeds: vec!["a.social".to_string(), "b.social".to_string()],
+            depth,
+            max,
+            deny: vec!["bad.social".to_string()],
+            parallel: 2,
+        };
+        let mut crawl = |crawler: Crawler| -> Vec<String> {
+            let mut names: Vec<String> = rt
+                .block_on(crawler.crawl_with(|d, f| fake_probe(&network, d, f)))
+                .into_iter()
+                .map(|c| c.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        // launch test
+        // seeds only
+        assert_eq!(crawl(crawler(0, 10)), vec!["a.social"]);
+        // peers of seeds, denied and already seen domains are skipped
+        assert_eq!(crawl(crawler(1, 10)), vec!["a.social", "c.social"]);
+        assert_eq!(
+            crawl(crawler(2, 10)),
+            vec!["a.social", "c.social", "d.social"]
+        );
+        // stop when enough instances are found
+        assert_eq!(crawl(crawler(2, 2)), vec!["a.social", "c.social"]);
+    }
+
+    #[test]
+    fn test_is_denied() {
+        // prepare
+        let crawler = Crawler {
+            seeds: vec![],
+            depth: 1,
+            max: 10,
+            deny: vec!["bad.social".to_string()],
+            parallel: 1,
+        };
+
+        // launch test
+        assert!(crawler.is_denied("bad.social"));
+        assert!(crawler.is_denied("sub.bad.social"));
+        assert!(!crawler.is_denied("notbad.social"));
+    }
+}
@@ -1,11 +1,16 @@
 // Mod get - used to get stats
 // Uses
-use crate::config::Config;
+use crate::config::ConfigError;
 use reqwest;
 use serde_json;
+use std::collections::HashMap;
 use std::fmt;
 use std::string::String;
-use tokio::runtime::Runtime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use url::Url;
 
 // Errors
 // Define ForgeError
@@ -24,7 +29,9 @@ pub enum GetError {
     ReqwestError(reqwest::Error),
     SerdeError(serde_json::error::Error),
     ForgeError,
-    IOError(std::io::Error),
+    DiscoveryError,
+    ConfigError(ConfigError),
+    StatusError(u16),
 }
 
 // implement From
@@ -42,59 +49,210 @@ impl From<serde_json::Error> for GetError {
     }
 }
 
-// IOError
-impl From<std::io::Error> for GetError {
-    fn from(err: std::io::Error) -> GetError {
-        GetError::IOError(err)
+// ConfigError
+impl From<ConfigError> for GetError {
+    fn from(err: ConfigError) -> GetError {
+        GetError::ConfigError(err)
     }
 }
 
-// Forge api url from kind + url
-fn forge_api_url(conf: &Config) -> Option<String> {
-    // match if stuff is supported
-    match conf.kind.as_str() {
-        "mastodon" | "pleroma" => {
-            return Some(format!("{}{}", conf.url, "/api/v1/instance"));
+// Structs - public
+// Response is the status of a request and the time it took
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub elapsed: Duration,
+}
+
+// Timings are network timings of an url, None when a step failed
+#[derive(Debug, Default)]
+pub struct Timings {
+    // time to resolve host
+    pub dns: Option<Duration>,
+    // time to open a tcp connection
+    pub connect: Option<Duration>,
+}
+
+// Fetcher holds the http client shared by all configs
+pub struct Fetcher {
+    // reqwest client, cloning it is cheap since it uses an Arc internally
+    client: reqwest::Client,
+    // max time spent on a request
+    timeout: Duration,
+    // max number of concurrent requests on the same host
+    per_host: usize,
+    // one semaphore per host, shared by traced fetchers
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+    // responses of requests sent, only for traced fetchers
+    trace: Option<Mutex<Vec<Response>>>,
+}
+
+// Implements for Fetcher
+impl Fetcher {
+    // new creates a fetcher allowing per_host concurrent requests on a host,
+    // each request taking at most timeout
+    pub fn new(per_host: usize, timeout: Duration) -> Result<Fetcher, GetError> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        Ok(Fetcher {
+            client,
+            timeout,
+            per_host,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+            trace: None,
+        })
+    }
+
+    // traced returns a fetcher sharing client and limits, recording responses of its requests
+    pub fn traced(&self) -> Fetcher {
+        Fetcher {
+            client: self.client.clone(),
+            timeout: self.timeout,
+            per_host: self.per_host,
+            hosts: self.hosts.clone(),
+            trace: Some(Mutex::new(Vec::new())),
         }
-        "mastodon_user" | "pleroma_user" => {
-            return conf
-                .get_user_id()
-                .and_then(|uid| Some(format!("{}{}{}", conf.url, "/api/v1/accounts/", uid)))
+    }
+
+    // responses returns responses recorded by a traced fetcher, in order
+    pub fn responses(&self) -> Vec<Response> {
+        match &self.trace {
+            // unwraping is ok here, lock is never held across a panic
+            Some(trace) => trace.lock().unwrap().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    // host_semaphore returns the semaphore associated to the host of url
+    fn host_semaphore(&self, url: &str) -> Arc<Semaphore> {
+        // use the whole url as key if host can not be extracted
+        let host = match Url::parse(url) {
+            Ok(u) => u.host_str().unwrap_or(url).to_string(),
+            Err(_) => url.to_string(),
+        };
+
+        // unwraping is ok here, lock is never held across a panic
+        let mut hosts = self.hosts.lock().unwrap();
+
+        hosts
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_host)))
+            .clone()
+    }
+
+    // send sends request to url, with headers, and parses the response as a json document,
+    // any status other than a success is an error
+    async fn send(
+        &self,
+        url: &str,
+        req: reqwest::RequestBuilder,
+        headers: &[(String, String)],
+    ) -> Result<serde_json::Value, GetError> {
+        let req = with_headers(req, headers);
+
+        // wait for our turn on this host
+        let semaphore = self.host_semaphore(url);
+        let _permit = semaphore.acquire().await;
+
+        let start = Instant::now();
+        let resp = req.send().await?;
+        let status = resp.status();
+
+        // extract resp to serde_json::Value
+        let text = resp.text().await?;
+
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().push(Response {
+                status: status.as_u16(),
+                elapsed: start.elapsed(),
+            });
         }
-        "plume" => return Some(format!("{}{}", conf.url, "/nodeinfo/2.0")),
-        "funkwhale" => return Some(format!("{}{}", conf.url, "/api/v1/instance/nodeinfo/2.0/")),
-        _ => {
-            return None;
+
+        if !status.is_success() {
+            return Err(GetError::StatusError(status.as_u16()));
         }
-    };
-}
 
-// Functions - Public
-// get_data is used to fetch remote data about a specified config
-pub fn get_data(conf: &Config) -> Result<serde_json::Value, GetError> {
-    // prepare a tokio runtime
-    let mut rt = match Runtime::new() {
-        Ok(rt) => rt,
-        Err(e) => return Err(GetError::IOError(e)),
-    };
-
-    // forge uri
-    let uri = match forge_api_url(&conf) {
-        Some(uri) => uri,
-        None => {
-            error!("Error forging url for {}", conf.kind);
-            return Err(GetError::ForgeError);
+        serde_json::from_str(text.as_str()).map_err(GetError::from)
+    }
+
+    // get_json is used to fetch a remote json document, headers are added to the request
+    pub async fn get_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<serde_json::Value, GetError> {
+        self.send(url, self.client.get(url), headers).await
+    }
+
+    // post_json is used to fetch a remote json document from an api expecting a json body
+    pub async fn post_json(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+        headers: &[(String, String)],
+    ) -> Result<serde_json::Value, GetError> {
+        self.send(url, self.post(url, body), headers).await
+    }
+
+    // timings times name resolution and tcp connection to the host of url,
+    // tls handshake is not timed since it is hidden by the http client
+    pub async fn timings(&self, url: &str) -> Timings {
+        let mut timings = Timings::default();
+
+        let (host, port) = match Url::parse(url) {
+            Ok(u) => match (u.host_str(), u.port_or_known_default()) {
+                (Some(host), Some(port)) => (host.to_string(), port),
+                _ => return timings,
+            },
+            Err(_) => return timings,
+        };
+
+        // dns
+        let start = Instant::now();
+        let addr = match tokio::time::timeout(
+            self.timeout,
+            tokio::net::lookup_host((host.as_str(), port)),
+        )
+        .await
+        {
+            Ok(Ok(mut addrs)) => addrs.next(),
+            _ => None,
+        };
+        if addr.is_some() {
+            timings.dns = Some(start.elapsed());
         }
-    };
 
-    // get request
-    let resp = rt.block_on(reqwest::get(uri.as_str()))?;
+        // tcp connection, closed right away
+        if let Some(addr) = addr {
+            let start = Instant::now();
+            if let Ok(Ok(_)) = tokio::time::timeout(self.timeout, TcpStream::connect(addr)).await {
+                timings.connect = Some(start.elapsed());
+            }
+        }
 
-    // extract resp to serde_json::Value
-    let text = rt.block_on(resp.text());
+        timings
+    }
 
-    text.map_err(|e| GetError::from(e))
-        .and_then(|s| serde_json::from_str(s.as_str()).map_err(|e| GetError::from(e)))
+    // post prepares a post request with a json body
+    fn post(&self, url: &str, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        self.client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+    }
+}
+
+// Functions - private
+// with_headers adds headers to a request
+fn with_headers(
+    mut req: reqwest::RequestBuilder,
+    headers: &[(String, String)],
+) -> reqwest::RequestBuilder {
+    for (name, value) in headers {
+        req = req.header(name.as_str(), value.as_str());
+    }
+
+    req
 }
 
 // Tests
@@ -102,59 +260,144 @@ pub fn get_data(conf: &Config) -> Result<serde_json::Value, GetError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::create_test_config;
+    use std::io::{Read, Write};
+    use tokio::runtime::Runtime;
 
     #[test]
-    fn test_forge_url_ok() {
+    fn test_get_json_ok() {
         // prepare
-        let test_ok = create_test_config();
+        let mut rt = Runtime::new().unwrap();
+        let fetcher = Fetcher::new(1, Duration::from_secs(5)).unwrap();
 
-        // launch tests and check results
-        match forge_api_url(&test_ok) {
-            Some(url) => assert_eq!(url, "https://rage.love/api/v1/instance"),
-            None => panic!("Error in forge_url test"),
-        };
+        // launch test and check result
+        match rt.block_on(fetcher.get_json("https://rage.love/api/v1/instance", &[])) {
+            // TODO: better test here
+            Ok(data) => assert_ne!(data["stats"]["user_count"], 0),
+            Err(e) => panic!(e),
+        }
     }
 
     #[test]
     #[should_panic]
-    fn test_forge_url_nok() {
+    fn test_get_json_nok() {
         // prepare
-        let mut test_nok = create_test_config();
-        test_nok.kind = "shit".to_string();
+        let mut rt = Runtime::new().unwrap();
+        let fetcher = Fetcher::new(1, Duration::from_secs(5)).unwrap();
 
         // launch test
-        match forge_api_url(&test_nok) {
-            Some(_) => assert!(true),
-            None => panic!("Kind of config {} is not supported", &test_nok.kind),
-        };
+        match rt.block_on(fetcher.get_json("ohno", &[])) {
+            // url is not valid
+            Ok(_) => assert!(true),
+            Err(_) => panic!("Error, this url is not valid"),
+        }
+    }
+
+    // serve answers requests on /ok with a 200, and others with a 404, for 5 seconds
+    fn serve() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        // never wait on a client, a test must fail and not hang
+        listener.set_nonblocking(true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        std::thread::spawn(move || {
+            while Instant::now() < deadline {
+                let mut stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => {
+                        std::thread::sleep(Duration::from_millis(10));
+                        continue;
+                    }
+                };
+                stream.set_nonblocking(false).unwrap();
+                stream
+                    .set_read_timeout(Some(Duration::from_secs(1)))
+                    .unwrap();
+
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                if n == 0 {
+                    continue;
+                }
+
+                let status = if buf[..n].starts_with(b"GET /ok ") {
+                    "200 OK"
+                } else {
+                    "404 Not Found"
+                };
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+                        status
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+
+        url
     }
 
     #[test]
-    fn test_get_data_ok() {
+    fn test_traced() {
         // prepare
-        let test_ok = create_test_config();
+        let mut rt = Runtime::new().unwrap();
+        let fetcher = Fetcher::new(1, Duration::from_secs(5)).unwrap();
+        let traced = fetcher.traced();
+        let url = serve();
 
-        // launch test and check result
-        match get_data(&test_ok) {
-            // TODO: better test here
-            Ok(data) => assert_ne!(data["stats"]["user_count"], 0),
-            Err(e) => panic!(e),
+        // launch test
+        assert!(rt
+            .block_on(traced.get_json(&format!("{}/ok", url), &[]))
+            .is_ok());
+
+        // a json body does not make an error a success
+        match rt.block_on(traced.get_json(&format!("{}/nope", url), &[])) {
+            Err(GetError::StatusError(404)) => (),
+            other => panic!("404 should be an error, got {:?}", other),
         }
+
+        let statuses: Vec<u16> = traced.responses().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![200, 404]);
+
+        // only traced fetchers record responses, semaphores are shared
+        assert!(fetcher.responses().is_empty());
+        assert!(Arc::ptr_eq(
+            &fetcher.host_semaphore(&url),
+            &traced.host_semaphore(&url)
+        ));
     }
 
     #[test]
-    #[should_panic]
-    fn test_get_data_nok() {
+    fn test_timings() {
         // prepare
-        let mut test_nok = create_test_config();
-        test_nok.kind = "shit".to_string();
+        let mut rt = Runtime::new().unwrap();
+        let fetcher = Fetcher::new(1, Duration::from_secs(5)).unwrap();
+        let url = serve();
 
         // launch test
-        match get_data(&test_nok) {
-            // instance kind not supported
-            Ok(_) => assert!(true),
-            Err(_) => panic!("Error, this kind of config is not supported"),
-        }
+        let timings = rt.block_on(fetcher.timings(&url));
+        assert!(timings.dns.is_some());
+        assert!(timings.connect.is_some());
+
+        // nothing to time
+        let timings = rt.block_on(fetcher.timings("ohno"));
+        assert!(timings.dns.is_none());
+        assert!(timings.connect.is_none());
+    }
+
+    #[test]
+    fn test_host_semaphore() {
+        // prepare
+        let fetcher = Fetcher::new(2, Duration::from_secs(5)).unwrap();
+
+        // same host, same semaphore
+        let a = fetcher.host_semaphore("https://rage.love/api/v1/instance");
+        let b = fetcher.host_semaphore("https://rage.love/api/v1/accounts/32098");
+        let c = fetcher.host_semaphore("https://demo.funkwhale.audio/api/v1/instance");
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(a.available_permits(), 2);
     }
 }
@@ -2,25 +2,75 @@
 // Uses
 use crate::influx::translate::{DataField, Measurement};
 use influxdb::InfluxDbWriteable;
-use influxdb::{Client, Error, Timestamp};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::runtime::Runtime;
+use influxdb::{Client, Error, Query, Timestamp, WriteQuery};
+use reqwest::StatusCode;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-// Functions - public
-// create_influx_client creates an influx db client
-pub fn create_influx_client<'a>(
-    username: &'a str,
-    password: &'a str,
-    database: &'a str,
-    host: &'a str,
-) -> Client {
-    influxdb::Client::new(host, database).with_auth(username, password)
+// Enums - public
+// Precision of timestamps written to InfluxDB 2.x
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
 }
 
-// push_measurement push measurement paramater to output configured in client
-pub fn push_measurement(client: &Client, measurement: Measurement) -> Result<String, Error> {
+// Implements for Precision
+impl Precision {
+    // as_param returns the precision as expected by the write api
+    pub fn as_param(self) -> &'static str {
+        match self {
+            Precision::Nanoseconds => "ns",
+            Precision::Microseconds => "us",
+            Precision::Milliseconds => "ms",
+            Precision::Seconds => "s",
+        }
+    }
+
+    // timestamp converts a duration since EPOCH to a timestamp using this precision
+    fn timestamp(self, since_epoch: Duration) -> Timestamp {
+        match self {
+            Precision::Nanoseconds => Timestamp::Nanoseconds(since_epoch.as_nanos()),
+            Precision::Microseconds => Timestamp::Microseconds(since_epoch.as_micros()),
+            Precision::Milliseconds => Timestamp::Milliseconds(since_epoch.as_millis()),
+            Precision::Seconds => Timestamp::Seconds(since_epoch.as_secs() as u128),
+        }
+    }
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Precision, String> {
+        match s {
+            "ns" => Ok(Precision::Nanoseconds),
+            "us" => Ok(Precision::Microseconds),
+            "ms" => Ok(Precision::Milliseconds),
+            "s" => Ok(Precision::Seconds),
+            _ => Err(format!("precision {} not supported", s)),
+        }
+    }
+}
+
+// Structs - public
+// Client2 is a client for the InfluxDB 2.x write api
+pub struct Client2 {
+    client: reqwest::Client,
+    // url of the write endpoint
+    url: String,
+    org: String,
+    bucket: String,
+    token: String,
+    precision: Precision,
+}
+
+// Functions - private
+// build_query converts a measurement into a write query
+fn build_query(timestamp: Timestamp, measurement: &Measurement) -> WriteQuery {
     // init query
-    let mut query = Timestamp::Microseconds(ts()).into_query(&measurement.key);
+    let mut query = timestamp.into_query(&measurement.key);
 
     // for each field add to query
     for (key, value) in measurement.fields.iter() {
@@ -29,6 +79,10 @@ pub fn push_measurement(client: &Client, measurement: Measurement) -> Result<Str
             DataField::Int(value) => {
                 query = query.add_field(key, value);
             }
+            // If Float
+            DataField::Float(value) => {
+                query = query.add_field(key, value);
+            }
             // If Str a clone is needed
             DataField::Str(value) => {
                 query = query.add_field(key, value.clone());
@@ -37,18 +91,224 @@ pub fn push_measurement(client: &Client, measurement: Measurement) -> Result<Str
     }
 
     // for each tag add to query
-    for (key, value) in measurement.tags.into_iter() {
-        query = query.add_tag(key, value);
+    for (key, value) in measurement.tags.iter() {
+        query = query.add_tag(key, value.clone());
     }
 
-    // write query, handle future simply with block on
-    Runtime::new().unwrap().block_on(client.query(&query))
+    query
+}
+
+// to_line_protocol converts measurements into InfluxDB line protocol
+fn to_line_protocol(
+    measurements: &[Measurement],
+    precision: Precision,
+    since_epoch: Duration,
+) -> Result<String, Error> {
+    let lines = measurements
+        .iter()
+        .map(|m| {
+            build_query(precision.timestamp(time_of(m, since_epoch)), m)
+                .build()
+                .map(|q| q.get())
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    Ok(lines.join("\n"))
 }
 
-// timestamp will compute from EPOCH timestamp
-fn ts() -> u128 {
+// since_epoch returns the duration elapsed since EPOCH
+fn since_epoch() -> Duration {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Time goes backward ?")
-        .as_millis()
+}
+
+// time_of returns when measurement happened, measurements with their own timestamp
+// are written at that time, others now (since_epoch)
+fn time_of(measurement: &Measurement, since_epoch: Duration) -> Duration {
+    measurement
+        .timestamp
+        .map(Duration::from_secs)
+        .unwrap_or(since_epoch)
+}
+
+// build_query_v1 converts a measurement into a write query for InfluxDB 1.x,
+// written with a millisecond precision
+fn build_query_v1(measurement: &Measurement, since_epoch: Duration) -> WriteQuery {
+    build_query(
+        Precision::Milliseconds.timestamp(time_of(measurement, since_epoch)),
+        measurement,
+    )
+}
+
+// Functions - public
+// create_influx_client creates an influx db client
+pub fn create_influx_client<'a>(
+    username: &'a str,
+    password: &'a str,
+    database: &'a str,
+    host: &'a str,
+) -> Client {
+    influxdb::Client::new(host, database).with_auth(username, password)
+}
+
+// create_influx2_client creates a client for InfluxDB 2.x, each write taking at most timeout
+pub fn create_influx2_client(
+    host: &str,
+    org: &str,
+    bucket: &str,
+    token: &str,
+    precision: Precision,
+    timeout: Duration,
+) -> Result<Client2, Error> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| Error::ConnectionError {
+            error: e.to_string(),
+        })?;
+
+    Ok(Client2 {
+        client,
+        url: format!("{}/api/v2/write", host.trim_end_matches('/')),
+        org: org.to_string(),
+        bucket: bucket.to_string(),
+        token: token.to_string(),
+        precision,
+    })
+}
+
+// push_measurement push measurement paramater to output configured in client
+pub async fn push_measurement(client: &Client, measurement: &Measurement) -> Result<String, Error> {
+    let query = build_query_v1(measurement, since_epoch());
+
+    // write query
+    client.query(&query).await
+}
+
+// push_measurements_v2 push all measurements in one request to the InfluxDB 2.x write api
+pub async fn push_measurements_v2(
+    client: &Client2,
+    measurements: &[Measurement],
+) -> Result<(), Error> {
+    let body = to_line_protocol(measurements, client.precision, since_epoch())?;
+
+    let resp = client
+        .client
+        .post(client.url.as_str())
+        .query(&[
+            ("org", client.org.as_str()),
+            ("bucket", client.bucket.as_str()),
+            ("precision", client.precision.as_param()),
+        ])
+        .header("Authorization", format!("Token {}", client.token))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| Error::ConnectionError {
+            error: e.to_string(),
+        })?;
+
+    match resp.status() {
+        s if s.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED => Err(Error::AuthenticationError),
+        StatusCode::FORBIDDEN => Err(Error::AuthorizationError),
+        s => Err(Error::DatabaseError {
+            error: format!("{}: {}", s, resp.text().await.unwrap_or_default()),
+        }),
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precision() {
+        assert_eq!(Precision::from_str("us").unwrap(), Precision::Microseconds);
+        assert!(Precision::from_str("h").is_err());
+
+        let since_epoch = Duration::from_secs(1589000000);
+        assert_eq!(
+            Precision::Seconds.timestamp(since_epoch),
+            Timestamp::Seconds(1589000000)
+        );
+        assert_eq!(
+            Precision::Milliseconds.timestamp(since_epoch),
+            Timestamp::Milliseconds(1589000000000)
+        );
+    }
+
+    #[test]
+    fn test_to_line_protocol() {
+        // prepare
+        let mut measurement = Measurement {
+            key: "mastodon".to_string(),
+            ..Default::default()
+        };
+        measurement
+            .tags
+            .insert("name".to_string(), "rage.love".to_string());
+        measurement
+            .fields
+            .insert("users".to_string(), DataField::Int(31));
+
+        // launch test
+        let lines = to_line_protocol(
+            &[measurement],
+            Precision::Seconds,
+            Duration::from_secs(1589000000),
+        )
+        .unwrap();
+
+        assert_eq!(lines, "mastodon,name=rage.love users=31i 1589000000");
+
+        // measurement with its own timestamp
+        let mut measurement = Measurement {
+            key: "mastodon_activity".to_string(),
+            timestamp: Some(1588550400),
+            ..Default::default()
+        };
+        measurement
+            .fields
+            .insert("logins".to_string(), DataField::Int(12));
+
+        let lines = to_line_protocol(
+            &[measurement],
+            Precision::Milliseconds,
+            Duration::from_secs(1589000000),
+        )
+        .unwrap();
+
+        assert_eq!(lines, "mastodon_activity logins=12i 1588550400000");
+    }
+
+    #[test]
+    fn test_build_query_v1() {
+        // prepare
+        let mut measurement = Measurement {
+            key: "mastodon".to_string(),
+            ..Default::default()
+        };
+        measurement
+            .fields
+            .insert("users".to_string(), DataField::Int(31));
+        let since_epoch = Duration::from_secs(1589000000);
+
+        // launch test, live and backfilled points use the same precision
+        let query = build_query_v1(&measurement, since_epoch);
+        assert_eq!(
+            query.build().unwrap().get(),
+            "mastodon users=31i 1589000000000"
+        );
+
+        measurement.timestamp = Some(1588550400);
+        let query = build_query_v1(&measurement, since_epoch);
+        assert_eq!(
+            query.build().unwrap().get(),
+            "mastodon users=31i 1588550400000"
+        );
+    }
 }
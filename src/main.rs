@@ -2,12 +2,18 @@
 // Extern crates
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate clap;
 
 // mods
 mod app;
 mod config;
+mod crawler;
 mod get;
 mod influx;
+mod scheduler;
+mod sink;
+mod source;
 
 // Uses
 use clap::{App, Arg};
@@ -44,6 +50,105 @@ and push all the results into an InfluxDB server"#,
                 .help("Path to directory containing config.toml files")
                 .default_value("/etc/fediwatcher/conf.d"),
         )
+        // daemon mode
+        .arg(
+            Arg::with_name("daemon")
+                .short("d")
+                .long("daemon")
+                .help("Keep running and collect metrics periodically"),
+        )
+        // default interval used in daemon mode
+        .arg(
+            Arg::with_name("interval")
+                .short("i")
+                .long("interval")
+                .env("INTERVAL")
+                .default_value("15m")
+                .help("Default interval between two runs in daemon mode (eg: 5m, 1h)"),
+        )
+        // concurrency
+        .arg(
+            Arg::with_name("parallel")
+                .short("p")
+                .long("parallel")
+                .env("PARALLEL")
+                .default_value("16")
+                .help("Max number of configs collected concurrently"),
+        )
+        .arg(
+            Arg::with_name("per_host")
+                .long("per-host")
+                .env("PER_HOST")
+                .default_value("2")
+                .help("Max number of concurrent requests on the same host"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .short("t")
+                .long("timeout")
+                .env("TIMEOUT")
+                .default_value("30s")
+                .help("Max time spent on a request (eg: 30s, 1m)"),
+        )
+        .arg(
+            Arg::with_name("network_timings")
+                .long("network-timings")
+                .help("Also time name resolution and tcp connection of each config url"),
+        )
+        // crawler
+        .arg(
+            Arg::with_name("crawl_seeds")
+                .long("crawl-seed")
+                .env("CRAWL_SEEDS")
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Instances the crawler starts from, enables crawl mode (eg: rage.love)"),
+        )
+        .arg(
+            Arg::with_name("crawl_depth")
+                .long("crawl-depth")
+                .env("CRAWL_DEPTH")
+                .default_value("1")
+                .help("Max number of hops from seed instances"),
+        )
+        .arg(
+            Arg::with_name("crawl_max")
+                .long("crawl-max")
+                .env("CRAWL_MAX")
+                .default_value("100")
+                .help("Max number of instances found by the crawler"),
+        )
+        .arg(
+            Arg::with_name("crawl_deny")
+                .long("crawl-deny")
+                .env("CRAWL_DENY")
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .help("Domains never crawled, subdomains included"),
+        )
+        // sinks
+        .arg(
+            Arg::with_name("sinks")
+                .short("s")
+                .long("sink")
+                .env("SINKS")
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .possible_values(&sink::SUPPORTED)
+                .default_value("influx")
+                .help("Where to send metrics, can be used multiple times (eg: influx,prometheus)"),
+        )
+        // prometheus exporter
+        .arg(
+            Arg::with_name("prometheus_listen")
+                .long("prometheus-listen")
+                .env("PROMETHEUS_LISTEN")
+                .default_value("0.0.0.0:9430")
+                .help("Address used to serve metrics when using the prometheus sink"),
+        )
         // influxdb
         // database
         .arg(
@@ -73,6 +178,41 @@ and push all the results into an InfluxDB server"#,
                 .default_value("http://localhost:8086")
                 .help("URL of InfluxDB endpoint"),
         )
+        // influxdb 2.x
+        // organization
+        .arg(
+            Arg::with_name("influx_org")
+                .long("influx-org")
+                .env("INFLUXDB_ORG")
+                .default_value("fediwatcher")
+                .help("Name of the InfluxDB 2.x organization"),
+        )
+        // bucket
+        .arg(
+            Arg::with_name("influx_bucket")
+                .long("influx-bucket")
+                .env("INFLUXDB_BUCKET")
+                .default_value("fediwatcher")
+                .help("Name of the InfluxDB 2.x bucket"),
+        )
+        // token
+        .arg(
+            Arg::with_name("influx_token")
+                .long("influx-token")
+                .env("INFLUXDB_TOKEN")
+                .hide_env_values(true)
+                .takes_value(true)
+                .help("API token used to write into the InfluxDB 2.x bucket"),
+        )
+        // precision
+        .arg(
+            Arg::with_name("influx_precision")
+                .long("influx-precision")
+                .env("INFLUXDB_PRECISION")
+                .possible_values(&["ns", "us", "ms", "s"])
+                .default_value("ms")
+                .help("Precision of timestamps written to InfluxDB 2.x"),
+        )
         // get all the matches and ! good to go !
         .get_matches();
 
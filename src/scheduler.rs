@@ -0,0 +1,109 @@
+// Mod scheduler - used to run configs at their own interval in daemon mode
+// Uses
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+// Structs - private
+// Job represents a config waiting for its next run
+struct Job {
+    // index of the config
+    index: usize,
+    // time between two runs
+    interval: Duration,
+    // next time this job needs to run
+    next: Instant,
+}
+
+// Structs - public
+// Scheduler keeps track of when each config needs to be collected
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+// Implements for Scheduler
+impl Scheduler {
+    // new creates a scheduler, all configs are due right away
+    pub fn new(intervals: Vec<Duration>, now: Instant) -> Scheduler {
+        let jobs = intervals
+            .into_iter()
+            .enumerate()
+            .map(|(index, interval)| Job {
+                index,
+                interval,
+                next: now,
+            })
+            .collect();
+
+        Scheduler { jobs }
+    }
+
+    // due returns indexes of configs to run now and reschedule them
+    pub fn due(&mut self, now: Instant) -> Vec<usize> {
+        let mut due = Vec::new();
+
+        for job in self.jobs.iter_mut() {
+            if job.next <= now {
+                due.push(job.index);
+                job.next += job.interval;
+                // if a run took longer than the interval, do not try to catch up
+                if job.next <= now {
+                    job.next = now + job.interval;
+                }
+            }
+        }
+
+        due
+    }
+
+    // next_wakeup returns the earliest time a config will be due
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        self.jobs.iter().map(|j| j.next).min()
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_due() {
+        // prepare
+        let now = Instant::now();
+        let mut scheduler = Scheduler::new(
+            vec![Duration::from_secs(300), Duration::from_secs(900)],
+            now,
+        );
+
+        // everything is due on first run
+        assert_eq!(scheduler.due(now), vec![0, 1]);
+        assert_eq!(
+            scheduler.next_wakeup(),
+            Some(now + Duration::from_secs(300))
+        );
+
+        // only the fastest one after 5 minutes
+        assert_eq!(scheduler.due(now + Duration::from_secs(300)), vec![0]);
+
+        // both after 15 minutes
+        assert_eq!(scheduler.due(now + Duration::from_secs(900)), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_scheduler_late() {
+        // prepare
+        let now = Instant::now();
+        let mut scheduler = Scheduler::new(vec![Duration::from_secs(60)], now);
+        scheduler.due(now);
+
+        // a late run does not trigger catch up runs
+        let late = now + Duration::from_secs(200);
+        assert_eq!(scheduler.due(late), vec![0]);
+        assert_eq!(
+            scheduler.next_wakeup(),
+            Some(late + Duration::from_secs(60))
+        );
+        assert!(scheduler.due(late).is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+// Mod influxdb - sinks pushing measurements to an InfluxDB server
+// Uses
+use crate::config::Config;
+use crate::influx;
+use crate::influx::push::{Client2, Precision};
+use crate::influx::translate::Measurement;
+use crate::sink::{Sink, SinkError};
+use futures::future::{BoxFuture, FutureExt};
+use influxdb::Client;
+use std::time::Duration;
+
+// Structs - public
+// InfluxSink pushes measurements to InfluxDB
+pub struct InfluxSink {
+    client: Client,
+    // max time spent on a push
+    timeout: Duration,
+}
+
+// Influx2Sink pushes measurements to InfluxDB 2.x
+pub struct Influx2Sink {
+    client: Client2,
+}
+
+// Implements for InfluxSink
+impl InfluxSink {
+    // new creates an influx sink from command line options, pushes taking at most timeout
+    pub fn new(matches: &clap::ArgMatches, timeout: Duration) -> InfluxSink {
+        let client = influx::push::create_influx_client(
+            // unwraping is ok here since defaults value are set
+            matches.value_of("influx_user").unwrap(),
+            matches.value_of("influx_password").unwrap(),
+            matches.value_of("influx_database").unwrap(),
+            matches.value_of("influx_host").unwrap(),
+        );
+
+        InfluxSink { client, timeout }
+    }
+}
+
+impl Sink for InfluxSink {
+    fn name(&self) -> &str {
+        "influx"
+    }
+
+    fn push<'a>(
+        &'a self,
+        _conf: &'a Config,
+        measurements: &'a [Measurement],
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        async move {
+            for measurement in measurements {
+                // the influxdb client has no timeout of its own
+                tokio::time::timeout(
+                    self.timeout,
+                    influx::push::push_measurement(&self.client, measurement),
+                )
+                .await
+                .map_err(|e| ::influxdb::Error::ConnectionError {
+                    error: e.to_string(),
+                })??;
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+// Implements for Influx2Sink
+impl Influx2Sink {
+    // new creates an influx 2.x sink from command line options, pushes taking at most timeout
+    pub fn new(matches: &clap::ArgMatches, timeout: Duration) -> Result<Influx2Sink, SinkError> {
+        // unwraping is ok here since defaults value are set
+        let precision: Precision = matches
+            .value_of("influx_precision")
+            .unwrap()
+            .parse()
+            .map_err(SinkError::ConfigError)?;
+
+        // token has no sane default value
+        let token = match matches.value_of("influx_token") {
+            Some(token) => token,
+            None => {
+                return Err(SinkError::ConfigError(
+                    "an InfluxDB token is required by the influx2 sink".to_string(),
+                ))
+            }
+        };
+
+        let client = influx::push::create_influx2_client(
+            matches.value_of("influx_host").unwrap(),
+            matches.value_of("influx_org").unwrap(),
+            matches.value_of("influx_bucket").unwrap(),
+            token,
+            precision,
+            timeout,
+        )?;
+
+        Ok(Influx2Sink { client })
+    }
+}
+
+impl Sink for Influx2Sink {
+    fn name(&self) -> &str {
+        "influx2"
+    }
+
+    fn push<'a>(
+        &'a self,
+        _conf: &'a Config,
+        measurements: &'a [Measurement],
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        async move {
+            influx::push::push_measurements_v2(&self.client, measurements).await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,96 @@
+// Mod sink - used to send measurements to various outputs
+
+// Reexporting
+pub mod influxdb;
+pub mod prometheus;
+
+// Uses
+use crate::config::Config;
+use crate::influx::translate::Measurement;
+use futures::future::BoxFuture;
+use std::time::Duration;
+
+// Const
+// array of supported sinks
+pub const SUPPORTED: [&str; 3] = ["influx", "influx2", "prometheus"];
+
+// Errors
+// Define SinkError
+#[derive(Debug)]
+pub enum SinkError {
+    InfluxError(::influxdb::Error),
+    HyperError(hyper::Error),
+    AddrError(std::net::AddrParseError),
+    NotSupportedError(String),
+    DaemonRequiredError(String),
+    ConfigError(String),
+}
+
+// implement From
+// InfluxError
+impl From<::influxdb::Error> for SinkError {
+    fn from(err: ::influxdb::Error) -> SinkError {
+        SinkError::InfluxError(err)
+    }
+}
+
+// HyperError
+impl From<hyper::Error> for SinkError {
+    fn from(err: hyper::Error) -> SinkError {
+        SinkError::HyperError(err)
+    }
+}
+
+// AddrError
+impl From<std::net::AddrParseError> for SinkError {
+    fn from(err: std::net::AddrParseError) -> SinkError {
+        SinkError::AddrError(err)
+    }
+}
+
+// Traits
+// Sink is an output receiving all measurements
+pub trait Sink {
+    // name of the sink, used in logs
+    fn name(&self) -> &str;
+
+    // push sends a batch of measurements, all coming from conf, to the output
+    fn push<'a>(
+        &'a self,
+        conf: &'a Config,
+        measurements: &'a [Measurement],
+    ) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+// Functions - public
+// new creates the sink called name using command line options, pushes taking at most timeout,
+// it needs to be called inside a tokio runtime
+pub fn new(
+    name: &str,
+    matches: &clap::ArgMatches,
+    timeout: Duration,
+) -> Result<Box<dyn Sink>, SinkError> {
+    match name {
+        "influx" => Ok(Box::new(influxdb::InfluxSink::new(matches, timeout))),
+        "influx2" => Ok(Box::new(influxdb::Influx2Sink::new(matches, timeout)?)),
+        "prometheus" => Ok(Box::new(prometheus::PrometheusSink::new(matches)?)),
+        _ => Err(SinkError::NotSupportedError(name.to_string())),
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_nok() {
+        let matches = clap::App::new("test").get_matches_from(vec!["test"]);
+
+        match new("graphite", &matches, Duration::from_secs(30)) {
+            Err(SinkError::NotSupportedError(name)) => assert_eq!(name, "graphite"),
+            _ => panic!("graphite sink should not be supported"),
+        }
+    }
+}
@@ -0,0 +1,398 @@
+// Mod prometheus - sink exposing measurements to a prometheus server
+// Uses
+use crate::config::Config;
+use crate::influx::translate::{DataField, Measurement};
+use crate::sink::{Sink, SinkError};
+use futures::future::{BoxFuture, FutureExt};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+// Structs - private
+// ConfigId identifies a config, several configs can share a name (eg: an instance and its trends)
+#[derive(Clone, PartialEq)]
+struct ConfigId {
+    name: String,
+    kind: String,
+    url: String,
+}
+
+// Sample is the last known value of a serie
+struct Sample {
+    // config the sample comes from
+    config: ConfigId,
+    // extra label for info metrics (eg: version="3.1.3")
+    info: Option<(String, String)>,
+    // value, already formated
+    value: String,
+    // seconds since EPOCH of the measurement, if not now
+    timestamp: Option<u64>,
+}
+
+// Structs - public
+// Registry keeps the last value of each serie
+#[derive(Default)]
+pub struct Registry {
+    // metric name -> (labels -> sample)
+    metrics: RwLock<BTreeMap<String, BTreeMap<String, Sample>>>,
+}
+
+// PrometheusSink keeps measurements in a registry served on /metrics
+pub struct PrometheusSink {
+    registry: Arc<Registry>,
+}
+
+// Implements for Registry
+impl Registry {
+    // update stores all fields of measurement, coming from config, in registry
+    fn update(&self, config: &ConfigId, measurement: &Measurement) {
+        let labels = format_labels(measurement);
+
+        // unwraping is ok here, lock is never held across a panic
+        let mut metrics = self.metrics.write().unwrap();
+
+        for (field, value) in measurement.fields.iter() {
+            let name = metric_name(&measurement.key, field);
+
+            let (name, sample) = match value {
+                // int and float fields are plain gauges
+                DataField::Int(value) => (
+                    name,
+                    Sample {
+                        config: config.clone(),
+                        info: None,
+                        value: value.to_string(),
+                        timestamp: measurement.timestamp,
+                    },
+                ),
+                DataField::Float(value) => (
+                    name,
+                    Sample {
+                        config: config.clone(),
+                        info: None,
+                        value: value.to_string(),
+                        timestamp: measurement.timestamp,
+                    },
+                ),
+                // str fields are exposed as info metrics, with the value as a label
+                DataField::Str(value) => (
+                    format!("{}_info", name),
+                    Sample {
+                        config: config.clone(),
+                        info: Some((sanitize(field), value.clone())),
+                        value: "1".to_string(),
+                        timestamp: measurement.timestamp,
+                    },
+                ),
+            };
+
+            let series = metrics.entry(name).or_default();
+
+            // backfilled measurements never replace a more recent sample
+            if let (Some(ts), Some(old)) = (
+                sample.timestamp,
+                series.get(&labels).and_then(|s| s.timestamp),
+            ) {
+                if ts < old {
+                    continue;
+                }
+            }
+
+            // replacing the sample drops the old info label (eg: after an upgrade)
+            series.insert(labels.clone(), sample);
+        }
+    }
+
+    // replace drops all series of conf, then stores measurements of conf,
+    // so series missing from the last push of a config (eg: a tag out of trends) are removed
+    pub fn replace(&self, conf: &Config, measurements: &[Measurement]) {
+        let config = config_id(conf);
+
+        {
+            // unwraping is ok here, lock is never held across a panic
+            let mut metrics = self.metrics.write().unwrap();
+
+            for series in metrics.values_mut() {
+                series.retain(|_, sample| sample.config != config);
+            }
+            metrics.retain(|_, series| !series.is_empty());
+        }
+
+        for measurement in measurements {
+            self.update(&config, measurement);
+        }
+    }
+
+    // render formats all metrics using prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.read().unwrap();
+        let mut out = String::new();
+
+        for (name, series) in metrics.iter() {
+            out.push_str(&format!("# TYPE {} gauge\n", name));
+
+            for (labels, sample) in series.iter() {
+                let mut labels = labels.clone();
+
+                if let Some((key, value)) = &sample.info {
+                    if !labels.is_empty() {
+                        labels.push(',');
+                    }
+                    labels.push_str(&format!("{}=\"{}\"", key, escape(value)));
+                }
+
+                out.push_str(&format!("{}{{{}}} {}\n", name, labels, sample.value));
+            }
+        }
+
+        out
+    }
+}
+
+// Implements for PrometheusSink
+impl PrometheusSink {
+    // new creates a prometheus sink and starts the exporter,
+    // it needs to be called inside a tokio runtime
+    pub fn new(matches: &clap::ArgMatches) -> Result<PrometheusSink, SinkError> {
+        // the exporter is only useful if fediwatcher keeps running
+        if !matches.is_present("daemon") {
+            return Err(SinkError::DaemonRequiredError("prometheus".to_string()));
+        }
+
+        // unwraping is ok here since default value is set
+        let addr: SocketAddr = matches.value_of("prometheus_listen").unwrap().parse()?;
+        let registry = Arc::new(Registry::default());
+
+        let server = serve(registry.clone(), &addr)?;
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("Prometheus exporter error: {:?}", e);
+            }
+        });
+
+        Ok(PrometheusSink { registry })
+    }
+}
+
+impl Sink for PrometheusSink {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    fn push<'a>(
+        &'a self,
+        conf: &'a Config,
+        measurements: &'a [Measurement],
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        self.registry.replace(conf, measurements);
+
+        async { Ok(()) }.boxed()
+    }
+}
+
+// Functions - private
+// config_id returns the identity of conf
+fn config_id(conf: &Config) -> ConfigId {
+    ConfigId {
+        name: conf.name.clone(),
+        kind: conf.kind.clone(),
+        url: conf.url.clone(),
+    }
+}
+
+// sanitize replaces all chars not allowed in a metric or label name
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// metric_name builds a metric name from a measurement key and a field name
+fn metric_name(key: &str, field: &str) -> String {
+    sanitize(&format!("{}_{}", key, field))
+}
+
+// escape escapes a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// format_labels converts measurement tags to sorted prometheus labels
+fn format_labels(measurement: &Measurement) -> String {
+    let tags: BTreeMap<_, _> = measurement.tags.iter().collect();
+
+    tags.into_iter()
+        .map(|(key, value)| format!("{}=\"{}\"", sanitize(key), escape(value)))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// handle answers to a request on the exporter
+fn handle(registry: &Registry, req: Request<Body>) -> Response<Body> {
+    let mut resp = Response::default();
+
+    match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            resp.headers_mut()
+                .insert(CONTENT_TYPE, "text/plain; version=0.0.4".parse().unwrap());
+            *resp.body_mut() = Body::from(registry.render());
+        }
+        _ => *resp.status_mut() = StatusCode::NOT_FOUND,
+    }
+
+    resp
+}
+
+// serve binds addr and returns a future serving registry on /metrics,
+// it needs to be called inside a tokio runtime
+fn serve(
+    registry: Arc<Registry>,
+    addr: &SocketAddr,
+) -> Result<impl Future<Output = Result<(), hyper::Error>>, hyper::Error> {
+    let builder = Server::try_bind(addr)?;
+
+    let make_svc = make_service_fn(move |_conn| {
+        let registry = registry.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let registry = registry.clone();
+                async move { Ok::<_, Infallible>(handle(&registry, req)) }
+            }))
+        }
+    });
+
+    info!("Serving prometheus metrics on http://{}/metrics", addr);
+
+    Ok(builder.serve(make_svc))
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+
+    // create a measurement looking like a mastodon one
+    fn create_test_measurement(version: &str) -> Measurement {
+        let mut measurement = Measurement {
+            key: "mastodon".to_string(),
+            ..Default::default()
+        };
+        measurement
+            .tags
+            .insert("name".to_string(), "rage.love".to_string());
+        measurement
+            .tags
+            .insert("url".to_string(), "https://rage.love".to_string());
+        measurement
+            .fields
+            .insert("users".to_string(), DataField::Int(31));
+        measurement
+            .fields
+            .insert("version".to_string(), DataField::Str(version.to_string()));
+
+        measurement
+    }
+
+    #[test]
+    fn test_render() {
+        // prepare
+        let registry = Registry::default();
+        registry.replace(&create_test_config(), &[create_test_measurement("3.1.3")]);
+
+        // launch test
+        let out = registry.render();
+
+        assert!(out.contains("# TYPE mastodon_users gauge\n"));
+        assert!(out.contains("mastodon_users{name=\"rage.love\",url=\"https://rage.love\"} 31\n"));
+        assert!(out.contains(
+            "mastodon_version_info{name=\"rage.love\",url=\"https://rage.love\",version=\"3.1.3\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_update_replaces_info() {
+        // prepare
+        let registry = Registry::default();
+        let conf = create_test_config();
+        registry.replace(&conf, &[create_test_measurement("3.1.3")]);
+        registry.replace(&conf, &[create_test_measurement("3.1.4")]);
+
+        // launch test
+        let out = registry.render();
+
+        assert!(!out.contains("3.1.3"));
+        assert!(out.contains("version=\"3.1.4\""));
+    }
+
+    #[test]
+    fn test_update_keeps_most_recent() {
+        // prepare
+        let registry = Registry::default();
+        let mut recent = create_test_measurement("3.1.3");
+        recent.timestamp = Some(1589155200);
+        let mut old = create_test_measurement("3.1.3");
+        old.timestamp = Some(1588550400);
+        old.fields.insert("users".to_string(), DataField::Int(12));
+
+        // launch test, backfilled buckets come from the most recent one
+        registry.replace(&create_test_config(), &[recent, old]);
+
+        assert!(registry.render().contains("https://rage.love\"} 31\n"));
+    }
+
+    #[test]
+    fn test_replace_drops_stale_series() {
+        // prepare
+        let registry = Registry::default();
+        let instance = create_test_config();
+        let trends = Config::new(
+            "rage.love".to_string(),
+            "https://rage.love".to_string(),
+            "mastodon_trends".to_string(),
+            None,
+        );
+        let trend = |tag: &str| {
+            let mut measurement = create_test_measurement("3.1.3");
+            measurement.key = "mastodon_trends".to_string();
+            measurement.tags.insert("tag".to_string(), tag.to_string());
+            measurement
+        };
+
+        // launch test
+        registry.replace(&trends, &[trend("caturday"), trend("rustlang")]);
+        registry.replace(&instance, &[create_test_measurement("3.1.3")]);
+        registry.replace(&trends, &[trend("rustlang")]);
+
+        let out = registry.render();
+        assert!(!out.contains("caturday"));
+        assert!(out.contains("tag=\"rustlang\""));
+        // configs sharing a name are kept
+        assert!(out.contains("mastodon_users{name=\"rage.love\""));
+    }
+
+    #[test]
+    fn test_sanitize_and_escape() {
+        assert_eq!(
+            metric_name("mastodon_user", "followers"),
+            "mastodon_user_followers"
+        );
+        assert_eq!(
+            metric_name("demo.funk-whale", "users"),
+            "demo_funk_whale_users"
+        );
+        assert_eq!(
+            escape("a \"quoted\" \\ value"),
+            "a \\\"quoted\\\" \\\\ value"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+// Mod account - resolve accounts handles (eg: "@papey@rage.love") into ids of Mastodon API compatible platforms
+// Uses
+use crate::config::Config;
+use crate::get::{Fetcher, GetError};
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+use url::Url;
+
+// Functions - private
+// state_dir returns the private directory where state is kept between runs,
+// $XDG_STATE_HOME/fediwatcher or $HOME/.local/state/fediwatcher
+fn state_dir() -> Option<PathBuf> {
+    let absolute = |var: &str| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+    };
+
+    match absolute("XDG_STATE_HOME") {
+        Some(dir) => Some(dir.join("fediwatcher")),
+        None => absolute("HOME").map(|home| home.join(".local/state/fediwatcher")),
+    }
+}
+
+// create_private_dir creates dir, and its parents, readable by the current user only
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+
+    builder.create(dir)
+}
+
+// cache_path returns where the resolved id of conf is kept between runs, never in a shared
+// directory since the id is trusted, ids are local to an instance so the default one
+// depends on the instance host
+fn cache_path(conf: &Config, account: &str) -> Option<PathBuf> {
+    match conf.get_account_file() {
+        Some(path) => Some(PathBuf::from(path)),
+        None => {
+            let url = Url::parse(&conf.url).ok();
+            let host = url.as_ref().and_then(|u| u.host_str()).unwrap_or("local");
+            state_dir().map(|dir| dir.join(format!("{}.{}.account", host, account)))
+        }
+    }
+}
+
+// read_cache reads a resolved id, None if the account was never resolved
+fn read_cache(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content.trim().to_string()).filter(|id| !id.is_empty())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// write_cache writes a resolved id, then rename, to never leave a partial file behind
+fn write_cache(path: &Path, id: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, format!("{}\n", id))?;
+    fs::rename(tmp, path)
+}
+
+// acct_of reads the canonical account (eg: "papey@rage.love") from a WebFinger document
+fn acct_of(val: &serde_json::Value) -> Option<String> {
+    val["subject"]
+        .as_str()
+        .map(|s| s.trim_start_matches("acct:").to_string())
+}
+
+// resolve finds the id of account using WebFinger, then the lookup api of conf instance
+async fn resolve(
+    fetcher: &Fetcher,
+    conf: &Config,
+    account: &str,
+    headers: &[(String, String)],
+) -> Result<String, GetError> {
+    let (user, domain) = parse_account(account).ok_or(GetError::DiscoveryError)?;
+
+    // WebFinger is served by the account domain, over the same scheme as the instance
+    let scheme = Url::parse(&conf.url)
+        .map(|u| u.scheme().to_string())
+        .unwrap_or_else(|_| "https".to_string());
+    let webfinger = fetcher
+        .get_json(
+            &format!(
+                "{}://{}/.well-known/webfinger?resource=acct:{}@{}",
+                scheme, domain, user, domain
+            ),
+            &[],
+        )
+        .await?;
+    let acct = acct_of(&webfinger).ok_or(GetError::DiscoveryError)?;
+
+    let found = fetcher
+        .get_json(
+            &format!("{}{}{}", conf.url, "/api/v1/accounts/lookup?acct=", acct),
+            headers,
+        )
+        .await?;
+
+    match found["id"].as_str() {
+        Some(id) => Ok(id.to_string()),
+        None => {
+            error!("Account {} not found on {}", acct, conf.url);
+            Err(GetError::DiscoveryError)
+        }
+    }
+}
+
+// Functions - public
+// has_cache checks if the resolved id of conf can be kept between runs
+pub fn has_cache(conf: &Config) -> bool {
+    match conf.get_account() {
+        Some(account) => cache_path(conf, &account).is_some(),
+        None => true,
+    }
+}
+
+// parse_account splits an account handle into user and domain
+pub fn parse_account(account: &str) -> Option<(String, String)> {
+    let mut parts = account.trim().trim_start_matches('@').splitn(2, '@');
+
+    match (parts.next(), parts.next()) {
+        (Some(user), Some(domain)) if !user.is_empty() && !domain.is_empty() => {
+            Some((user.to_string(), domain.to_string()))
+        }
+        _ => None,
+    }
+}
+
+// user_id returns the id of the user of conf, set as user_id or resolved from account
+pub async fn user_id(
+    fetcher: &Fetcher,
+    conf: &Config,
+    headers: &[(String, String)],
+) -> Result<String, GetError> {
+    if let Some(id) = conf.get_user_id() {
+        return Ok(id);
+    }
+
+    let account = conf.get_account().ok_or(GetError::ForgeError)?;
+    let path = cache_path(conf, &account).ok_or(GetError::ForgeError)?;
+
+    match read_cache(&path) {
+        Ok(Some(id)) => return Ok(id),
+        Ok(None) => (),
+        Err(e) => warn!("Error reading account file {} : {}", path.display(), e),
+    }
+
+    let id = resolve(fetcher, conf, &account, headers).await?;
+    info!("Account {} resolved to id {}", account, id);
+
+    if let Err(e) = write_cache(&path, &id) {
+        warn!("Error writing account file {} : {}", path.display(), e);
+    }
+
+    Ok(id)
+}
+
+// remember replaces the cached id of conf, eg: when the account moved
+pub fn remember(conf: &Config, id: &str) {
+    if let Some(path) = conf.get_account().and_then(|a| cache_path(conf, &a)) {
+        if let Err(e) = write_cache(&path, id) {
+            warn!("Error writing account file {} : {}", path.display(), e);
+        }
+    }
+}
+
+// forget removes the cached id of conf, eg: when the account is gone
+pub fn forget(conf: &Config) {
+    if let Some(path) = conf.get_account().and_then(|a| cache_path(conf, &a)) {
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Error removing account file {} : {}", path.display(), e);
+            }
+        }
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_account() {
+        assert_eq!(
+            parse_account("@papey@rage.love"),
+            Some(("papey".to_string(), "rage.love".to_string()))
+        );
+        assert_eq!(
+            parse_account("papey@rage.love"),
+            Some(("papey".to_string(), "rage.love".to_string()))
+        );
+        assert!(parse_account("@papey").is_none());
+        assert!(parse_account("@papey@").is_none());
+    }
+
+    #[test]
+    fn test_acct_of() {
+        let webfinger = serde_json::json!({
+            "subject": "acct:papey@rage.love",
+            "aliases": ["https://rage.love/@papey"]
+        });
+
+        assert_eq!(acct_of(&webfinger), Some("papey@rage.love".to_string()));
+        assert!(acct_of(&serde_json::json!({ "error": "not found" })).is_none());
+    }
+
+    #[test]
+    fn test_cache_path() {
+        // prepare
+        env::set_var("XDG_STATE_HOME", "/var/lib/fediwatcher-test");
+        let parse = |url: &str, options: &str| -> Config {
+            toml::from_str(&format!(
+                r#"
+                name = "papey"
+                url = "{}"
+                kind = "mastodon_user"
+
+                [options]
+                account = "@papey@rage.love"
+                {}
+                "#,
+                url, options
+            ))
+            .expect("Error parsing test config")
+        };
+
+        // launch test
+        // same account, different instances
+        let a = parse("https://rage.love", "");
+        let b = parse("https://mastodon.social", "");
+        assert_eq!(
+            cache_path(&a, "@papey@rage.love").unwrap(),
+            PathBuf::from(
+                "/var/lib/fediwatcher-test/fediwatcher/rage.love.@papey@rage.love.account"
+            )
+        );
+        assert_ne!(
+            cache_path(&a, "@papey@rage.love"),
+            cache_path(&b, "@papey@rage.love")
+        );
+
+        // account_file takes precedence
+        let c = parse(
+            "https://rage.love",
+            r#"account_file = "/srv/papey.account""#,
+        );
+        assert_eq!(
+            cache_path(&c, "@papey@rage.love").unwrap(),
+            PathBuf::from("/srv/papey.account")
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        // prepare
+        let dir = env::temp_dir().join(format!("fediwatcher.account.{}", std::process::id()));
+        let path = dir.join("state").join("papey.account");
+        let _ = fs::remove_dir_all(&dir);
+
+        // launch test
+        assert_eq!(read_cache(&path).unwrap(), None);
+
+        write_cache(&path, "32098").unwrap();
+        assert_eq!(read_cache(&path).unwrap(), Some("32098".to_string()));
+
+        // state directory is private
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path.parent().unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        // cleanup
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+// Mod activitypub - source for any ActivityPub actor, using only ActivityPub documents
+// Uses
+use crate::config::Config;
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+
+// Statics
+// collections of an actor, in the order they are fetched
+static COLLECTIONS: [&str; 3] = ["followers", "following", "outbox"];
+
+// Structs - public
+// Actor collects metrics about an actor, url is the actor id (eg: "https://rage.love/users/papey")
+pub struct Actor;
+
+// Implements for Actor
+impl Source for Actor {
+    fn kind(&self) -> &'static str {
+        "activitypub_actor"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![conf.url.clone()])
+    }
+
+    // fetch gets the actor, then each of its collections
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let actor = fetcher
+                .get_json(&conf.url, &headers_for(conf, &conf.url)?)
+                .await?;
+
+            let mut data = vec![actor.clone()];
+            for name in COLLECTIONS.iter() {
+                let collection = match &actor[name] {
+                    // collections are usually links, possibly to another server
+                    serde_json::Value::String(url) => {
+                        match fetcher.get_json(url, &headers_for(conf, url)?).await {
+                            Ok(collection) => collection,
+                            // some servers hide collections, this is not an error
+                            Err(e) => {
+                                debug!("Error fetching {} of {} : {:?}", name, conf.url, e);
+                                serde_json::Value::Null
+                            }
+                        }
+                    }
+                    // but can be embedded
+                    other => other.clone(),
+                };
+                data.push(collection);
+            }
+
+            Ok(data)
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_actor(&data[1], &data[2], &data[3], conf)?])
+    }
+}
+
+// Functions - private
+// headers_for returns headers sent to url, asking for ActivityPub documents
+fn headers_for(conf: &Config, url: &str) -> Result<Vec<(String, String)>, GetError> {
+    let mut headers = conf.get_headers_for(url)?;
+    headers.push((
+        "Accept".to_string(),
+        "application/activity+json".to_string(),
+    ));
+
+    Ok(headers)
+}
+
+// new_from_actor will take collections of an actor and convert them into a Measurement,
+// with the same fields as Mastodon users
+fn new_from_actor(
+    followers: &serde_json::Value,
+    following: &serde_json::Value,
+    outbox: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    // url is not used as a tag for users
+    let mut measurement = Measurement {
+        key: conf.kind.clone(),
+        ..Default::default()
+    };
+    measurement
+        .tags
+        .insert("name".to_string(), conf.name.clone());
+
+    // add fields
+    // followers
+    measurement.add_int(
+        "followers",
+        followers["totalItems"].as_i64(),
+        "followers",
+        conf,
+    )?;
+
+    // following
+    measurement.add_int(
+        "following",
+        Some(following["totalItems"].as_i64().unwrap_or(0)),
+        "following",
+        conf,
+    )?;
+
+    // posts
+    measurement.add_int(
+        "statuses",
+        outbox["totalItems"].as_i64(),
+        "statuses count",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_actor() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.activitypub_actor.json")
+            .expect("Unable to read test file");
+
+        // actor, followers, following and outbox
+        let json: Vec<serde_json::Value> =
+            serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = Actor.translate(&json, &conf).unwrap().remove(0);
+
+        assert_eq!(measurement.fields["followers"], DataField::Int(51));
+        assert_eq!(measurement.fields["following"], DataField::Int(48));
+        assert_eq!(measurement.fields["statuses"], DataField::Int(1403));
+        assert!(!measurement.tags.contains_key("url"));
+
+        // hidden following
+        let measurement =
+            new_from_actor(&json[1], &serde_json::Value::Null, &json[3], &conf).unwrap();
+        assert_eq!(measurement.fields["following"], DataField::Int(0));
+
+        // hidden followers
+        assert!(new_from_actor(&serde_json::Value::Null, &json[2], &json[3], &conf).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// Mod admin - source for the Mastodon admin api, needs a token with admin:read scope
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{DataField, Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::json;
+use std::time::{Duration, SystemTime};
+
+// Statics
+// measures, as returned by the api
+static MEASURES: [&str; 5] = [
+    "active_users",
+    "new_users",
+    "interactions",
+    "opened_reports",
+    "resolved_reports",
+];
+
+// dimensions, as returned by the api
+static DIMENSIONS: [&str; 3] = ["software_versions", "languages", "sources"];
+
+// measures and dimensions are computed over this period, like the admin dashboard
+const PERIOD: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// Structs - public
+// Admin collects private metrics about an instance
+pub struct Admin;
+
+// Implements for Admin
+impl Source for Admin {
+    fn kind(&self) -> &'static str {
+        "mastodon_admin"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        match conf.get_token()? {
+            Some(_) => Ok(()),
+            None => {
+                error!(
+                    "token, token_env or token_file option is missing in config {}",
+                    conf.name
+                );
+                Err(ConfigError::MissingOptionError("token".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![
+            format!("{}{}", conf.url, "/api/v1/admin/measures"),
+            format!("{}{}", conf.url, "/api/v1/admin/dimensions"),
+        ])
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            // token is sent as a bearer token, like any other config
+            let headers = conf.get_headers()?;
+            let (start_at, end_at) = period(SystemTime::now());
+
+            let measures = json!({
+                "keys": MEASURES,
+                "start_at": start_at,
+                "end_at": end_at,
+            });
+            let dimensions = json!({
+                "keys": DIMENSIONS,
+                "start_at": start_at,
+                "end_at": end_at,
+                "limit": 10,
+            });
+
+            // bodies are in the same order as urls
+            let urls = self.urls(conf).ok_or(GetError::ForgeError)?;
+            let mut data = Vec::new();
+            for (url, body) in urls.iter().zip([measures, dimensions].iter()) {
+                data.push(fetcher.post_json(url, body, &headers).await?);
+            }
+
+            Ok(data)
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        let mut measurements = vec![new_from_measures(&data[0], conf)?];
+        measurements.append(&mut new_from_dimensions(&data[1], conf)?);
+
+        Ok(measurements)
+    }
+}
+
+// Functions - private
+// period returns start and end dates of the period ending at now (eg: "2020-05-11")
+fn period(now: SystemTime) -> (String, String) {
+    let date = |t: SystemTime| humantime::format_rfc3339_seconds(t).to_string()[..10].to_string();
+
+    (date(now - PERIOD), date(now))
+}
+
+// new_from_measures will take admin measures and convert them into a Measurement
+fn new_from_measures(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    let measures = match val.as_array() {
+        Some(measures) => measures,
+        None => return Err(TranslateError::new("measures", conf)),
+    };
+
+    // add fields, totals are sent as strings
+    for key in MEASURES.iter() {
+        let total = measures
+            .iter()
+            .find(|m| m["key"].as_str() == Some(key))
+            .and_then(|m| m["total"].as_str())
+            .and_then(|t| t.parse().ok());
+
+        measurement.add_int(key, total, key, conf)?;
+    }
+
+    Ok(measurement)
+}
+
+// new_from_dimensions will take admin dimensions and convert them into Measurements,
+// one per item, using the dimension as key and the item key as a tag
+fn new_from_dimensions(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Vec<Measurement>, TranslateError> {
+    let dimensions = match val.as_array() {
+        Some(dimensions) => dimensions,
+        None => return Err(TranslateError::new("dimensions", conf)),
+    };
+
+    let mut measurements = Vec::new();
+    for dimension in dimensions {
+        let name = match dimension["key"].as_str() {
+            Some(name) => name,
+            None => return Err(TranslateError::new("dimension key", conf)),
+        };
+
+        for item in dimension["data"].as_array().into_iter().flatten() {
+            let mut measurement = Measurement::new(conf);
+            measurement.key = format!("{}_{}", conf.kind, name);
+
+            match item["key"].as_str() {
+                Some(key) => measurement.tags.insert("key".to_string(), key.to_string()),
+                None => return Err(TranslateError::new("item key", conf)),
+            };
+
+            // values are sent as strings, software versions are not numbers
+            let value = match item["value"].as_str() {
+                Some(value) => match value.parse() {
+                    Ok(value) => DataField::Int(value),
+                    Err(_) => DataField::Str(value.to_string()),
+                },
+                None => return Err(TranslateError::new("item value", conf)),
+            };
+            measurement.fields.insert("value".to_string(), value);
+
+            measurements.push(measurement);
+        }
+    }
+
+    Ok(measurements)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use std::fs::File;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_period() {
+        let now = UNIX_EPOCH + Duration::from_secs(1589155200);
+
+        assert_eq!(
+            period(now),
+            ("2020-04-11".to_string(), "2020-05-11".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_from_measures() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_admin.measures.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_measures(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["active_users"], DataField::Int(21));
+        assert_eq!(measurement.fields["interactions"], DataField::Int(1290));
+        assert_eq!(measurement.fields["resolved_reports"], DataField::Int(2));
+    }
+
+    #[test]
+    fn test_new_from_dimensions() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_admin.dimensions.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurements = new_from_dimensions(&json, &conf).unwrap();
+
+        assert_eq!(measurements.len(), 6);
+        assert_eq!(measurements[0].key, "mastodon_software_versions");
+        assert_eq!(measurements[0].tags["key"], "mastodon");
+        assert_eq!(
+            measurements[0].fields["value"],
+            DataField::Str("3.1.3".to_string())
+        );
+        assert_eq!(measurements[3].key, "mastodon_languages");
+        assert_eq!(measurements[3].fields["value"], DataField::Int(431));
+    }
+}
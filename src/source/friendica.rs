@@ -0,0 +1,93 @@
+// Mod friendica - source for Friendica instances
+// Uses
+use crate::config::Config;
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Structs - public
+// Friendica collects metrics about a Friendica instance
+pub struct Friendica;
+
+// Implements for Friendica
+impl Source for Friendica {
+    fn kind(&self) -> &'static str {
+        "friendica"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/statistics.json")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_friendica(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_friendica will take statistics from friendica instance and convert it into a Measurement
+fn new_from_friendica(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // users
+    measurement.add_int("users", val["total_users"].as_i64(), "total users", conf)?;
+
+    // active users, same names as nodeinfo
+    measurement.add_opt_int("active_month", val["active_users_monthly"].as_i64());
+    measurement.add_opt_int("active_halfyear", val["active_users_halfyear"].as_i64());
+
+    // local_posts
+    measurement.add_int(
+        "local_posts",
+        val["local_posts"].as_i64(),
+        "local posts",
+        conf,
+    )?;
+
+    // local_comments, only on recent versions
+    measurement.add_opt_int("local_comments", val["local_comments"].as_i64());
+
+    // version
+    measurement.add_str("version", val["version"].as_str(), "version", conf)?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_friendica() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.friendica.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_friendica(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(842));
+        assert_eq!(measurement.fields["local_posts"], DataField::Int(120433));
+        assert_eq!(measurement.fields["active_month"], DataField::Int(97));
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("2023.05-1518".to_string())
+        );
+    }
+}
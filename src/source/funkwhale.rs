@@ -0,0 +1,106 @@
+// Mod funkwhale - source for Funkwhale instances
+// Uses
+use crate::config::Config;
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Structs - public
+// Funkwhale collects metrics about a Funkwhale instance
+pub struct Funkwhale;
+
+// Implements for Funkwhale
+impl Source for Funkwhale {
+    fn kind(&self) -> &'static str {
+        "funkwhale"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!(
+            "{}{}",
+            conf.url, "/api/v1/instance/nodeinfo/2.0/"
+        )])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_funkwhale(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_funkwhale will take data from funkwhale instance and convert it into a Measurement
+fn new_from_funkwhale(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // user count
+    measurement.add_int(
+        "users",
+        val["usage"]["users"]["total"].as_i64(),
+        "users total",
+        conf,
+    )?;
+
+    // library
+    // tracks total
+    measurement.add_int(
+        "tracks",
+        val["metadata"]["library"]["tracks"]["total"].as_i64(),
+        "tracks total",
+        conf,
+    )?;
+
+    // albums total
+    measurement.add_int(
+        "albums",
+        val["metadata"]["library"]["albums"]["total"].as_i64(),
+        "albums total",
+        conf,
+    )?;
+
+    // artists total
+    measurement.add_int(
+        "artists",
+        val["metadata"]["library"]["artists"]["total"].as_i64(),
+        "artists total",
+        conf,
+    )?;
+
+    // version
+    measurement.add_str("version", val["version"].as_str(), "version", conf)?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_funkwhale() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.funkwhale.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_funkwhale(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["albums"], DataField::Int(20));
+        assert_eq!(measurement.fields["artists"], DataField::Int(17));
+    }
+}
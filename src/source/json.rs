@@ -0,0 +1,203 @@
+// Mod json - generic source, fields are declared in config file
+// Uses
+use crate::config::{Config, ConfigError, FieldType};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Structs - public
+// Json collects fields declared in config from any json endpoint
+pub struct Json;
+
+// Implements for Json
+impl Source for Json {
+    fn kind(&self) -> &'static str {
+        "json"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        if conf.get_endpoint().is_none() {
+            error!("endpoint option is missing in config {}", conf.name);
+            return Err(ConfigError::MissingOptionError("endpoint".to_string()));
+        }
+
+        match conf.get_fields() {
+            Some(fields) if !fields.is_empty() => Ok(()),
+            _ => {
+                error!("fields option is missing in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("fields".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_endpoint()
+            .map(|endpoint| vec![format!("{}{}", conf.url, endpoint)])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_json(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// to_pointer converts a dotted path (eg: "$.stats.user_count" or "$.languages[0]")
+// to a JSON pointer, JSON pointers are returned as is
+fn to_pointer(path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+
+    path.trim_start_matches('$')
+        .split('.')
+        // indexes are keys of their own (eg: "languages[0]" is "languages" then "0")
+        .flat_map(|p| p.split('[').map(|k| k.trim_end_matches(']')))
+        .filter(|p| !p.is_empty())
+        // escape as described in RFC 6901
+        .map(|p| format!("/{}", p.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+// new_from_json will take data from any endpoint and convert it into a Measurement,
+// using mappings declared in config
+fn new_from_json(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // key from config, or kind
+    if let Some(key) = conf.get_key() {
+        measurement.key = key;
+    }
+
+    // add fields
+    for mapping in conf.get_fields().into_iter().flatten() {
+        let value = val.pointer(&to_pointer(&mapping.path));
+
+        match mapping.kind {
+            FieldType::Int => measurement.add_int(
+                &mapping.name,
+                value.and_then(|v| v.as_i64()),
+                &mapping.path,
+                conf,
+            )?,
+            FieldType::Float => measurement.add_float(
+                &mapping.name,
+                value.and_then(|v| v.as_f64()),
+                &mapping.path,
+                conf,
+            )?,
+            FieldType::String => {
+                // numbers and booleans are converted to string
+                let value = value.and_then(|v| match v {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    serde_json::Value::Bool(b) => Some(b.to_string()),
+                    _ => None,
+                });
+                measurement.add_str(&mapping.name, value.as_deref(), &mapping.path, conf)?
+            }
+        };
+    }
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    // create a json config reading a mastodon instance
+    fn create_test_json_config() -> Config {
+        toml::from_str(
+            r#"
+            name = "rage.love"
+            url = "https://rage.love"
+            kind = "json"
+
+            [options]
+            endpoint = "/api/v1/instance"
+            key = "instance"
+
+            [[options.fields]]
+            name = "users"
+            path = "/stats/user_count"
+            type = "int"
+
+            [[options.fields]]
+            name = "domains"
+            path = "$.stats.domain_count"
+            type = "float"
+
+            [[options.fields]]
+            name = "version"
+            path = "version"
+            type = "string"
+            "#,
+        )
+        .expect("Error parsing test config")
+    }
+
+    #[test]
+    fn test_to_pointer() {
+        assert_eq!(to_pointer("/stats/user_count"), "/stats/user_count");
+        assert_eq!(to_pointer("stats.user_count"), "/stats/user_count");
+        assert_eq!(to_pointer("$.languages.0"), "/languages/0");
+        assert_eq!(to_pointer("$.languages[0]"), "/languages/0");
+        assert_eq!(to_pointer("$[1].rules[0].text"), "/1/rules/0/text");
+        assert_eq!(to_pointer("a/b.c~d"), "/a~1b/c~0d");
+    }
+
+    #[test]
+    fn test_validate() {
+        // prepare
+        let mut conf = create_test_json_config();
+
+        // launch test
+        assert!(Json.validate(&conf).is_ok());
+        assert_eq!(
+            Json.urls(&conf).unwrap(),
+            vec!["https://rage.love/api/v1/instance"]
+        );
+
+        conf.options = None;
+        assert!(Json.validate(&conf).is_err());
+    }
+
+    #[test]
+    fn test_new_from_json() {
+        // prepare
+        let conf = create_test_json_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_json(&json, &conf).unwrap();
+
+        assert_eq!(measurement.key, "instance");
+        assert_eq!(measurement.fields["users"], DataField::Int(31));
+        assert_eq!(measurement.fields["domains"], DataField::Float(2948.0));
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("2.9.2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_from_json_missing_field() {
+        // prepare
+        let conf = create_test_json_config();
+        let json = serde_json::json!({ "stats": { "user_count": 31 } });
+
+        // launch test
+        assert!(new_from_json(&json, &conf).is_err());
+    }
+}
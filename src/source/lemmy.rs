@@ -0,0 +1,196 @@
+// Mod lemmy - sources for Lemmy instances
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Structs - public
+// Site collects metrics about a Lemmy instance
+pub struct Site;
+
+// Community collects metrics about a specific community on an instance
+pub struct Community;
+
+// Implements for Site
+impl Source for Site {
+    fn kind(&self) -> &'static str {
+        "lemmy"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api/v3/site")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_site(&data[0], conf)?])
+    }
+}
+
+// Implements for Community
+impl Source for Community {
+    fn kind(&self) -> &'static str {
+        "lemmy_community"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        match conf.get_community() {
+            Some(_) => Ok(()),
+            None => {
+                error!("community option is missing in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("community".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_community().map(|community| {
+            vec![format!(
+                "{}{}{}",
+                conf.url, "/api/v3/community?name=", community
+            )]
+        })
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_community(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// add_active_users adds active users per day, week, month and half year found in counts
+fn add_active_users(
+    measurement: &mut Measurement,
+    counts: &serde_json::Value,
+    conf: &Config,
+) -> Result<(), TranslateError> {
+    for period in ["day", "week", "month", "half_year"].iter() {
+        let field = format!("users_active_{}", period);
+        measurement.add_int(&field, counts[&field].as_i64(), &field, conf)?;
+    }
+
+    Ok(())
+}
+
+// new_from_site will take data from a lemmy instance and convert it into a Measurement
+fn new_from_site(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+    let counts = &val["site_view"]["counts"];
+
+    // add fields
+    // users
+    measurement.add_int("users", counts["users"].as_i64(), "users", conf)?;
+
+    // posts
+    measurement.add_int("posts", counts["posts"].as_i64(), "posts", conf)?;
+
+    // comments
+    measurement.add_int("comments", counts["comments"].as_i64(), "comments", conf)?;
+
+    // communities
+    measurement.add_int(
+        "communities",
+        counts["communities"].as_i64(),
+        "communities",
+        conf,
+    )?;
+
+    // active users
+    add_active_users(&mut measurement, counts, conf)?;
+
+    // version
+    measurement.add_str("version", val["version"].as_str(), "version", conf)?;
+
+    Ok(measurement)
+}
+
+// new_from_community will take data from a lemmy community and convert it into a Measurement
+fn new_from_community(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+    let counts = &val["community_view"]["counts"];
+
+    // add fields
+    // subscribers
+    measurement.add_int(
+        "subscribers",
+        counts["subscribers"].as_i64(),
+        "subscribers",
+        conf,
+    )?;
+
+    // posts
+    measurement.add_int("posts", counts["posts"].as_i64(), "posts", conf)?;
+
+    // comments
+    measurement.add_int("comments", counts["comments"].as_i64(), "comments", conf)?;
+
+    // active users
+    add_active_users(&mut measurement, counts, conf)?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_lemmy() {
+        // prepare
+        let conf = create_test_config();
+
+        let file =
+            File::open("./tests/json/test.new.from.lemmy.json").expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_site(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(48210));
+        assert_eq!(measurement.fields["communities"], DataField::Int(1289));
+        assert_eq!(
+            measurement.fields["users_active_half_year"],
+            DataField::Int(9871)
+        );
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("0.19.3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_from_lemmy_community() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.lemmy_community.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_community(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["subscribers"], DataField::Int(15402));
+        assert_eq!(measurement.fields["users_active_week"], DataField::Int(377));
+
+        // community option is mandatory
+        assert!(Community.validate(&conf).is_err());
+    }
+}
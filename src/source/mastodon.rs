@@ -0,0 +1,542 @@
+// Mod mastodon - sources for Mastodon API compatible platforms (Mastodon, Pleroma, Akkoma, GoToSocial)
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{DataField, Measurement, TranslateError};
+use crate::source::{account, Source};
+use futures::future::{BoxFuture, FutureExt};
+use std::time::SystemTime;
+
+// Statics
+// max number of statuses returned by the api in one request
+const MAX_STATUSES: u32 = 40;
+
+// Structs - public
+// Instance collects metrics about an instance
+pub struct Instance {
+    pub kind: &'static str,
+    // fields this kind of server may omit, recorded only when present
+    pub optional: &'static [&'static str],
+}
+
+// User collects metrics about a specific user on an instance
+pub struct User {
+    pub kind: &'static str,
+}
+
+// Activity collects weekly activity of an instance, one measurement per week
+pub struct Activity;
+
+// Implements for Instance
+impl Source for Instance {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api/v1/instance")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_instance(&data[0], self.optional, conf)?])
+    }
+}
+
+// Implements for User
+impl Source for User {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        validate_recent_statuses(conf)?;
+
+        match (conf.get_user_id(), conf.get_account()) {
+            (Some(_), _) => Ok(()),
+            (None, Some(acc)) if account::parse_account(&acc).is_none() => {
+                error!("account {} is not valid in config {}", acc, conf.name);
+                Err(ConfigError::InvalidOptionError("account".to_string()))
+            }
+            // the resolved id is never kept in a shared directory
+            (None, Some(_)) if !account::has_cache(conf) => {
+                error!(
+                    "account_file option is missing in config {}, and no state directory found",
+                    conf.name
+                );
+                Err(ConfigError::MissingOptionError("account_file".to_string()))
+            }
+            (None, Some(_)) => Ok(()),
+            (None, None) => {
+                error!(
+                    "user_id or account option is missing in config {}",
+                    conf.name
+                );
+                Err(ConfigError::MissingOptionError("user_id".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_user_id()
+            .map(|uid| vec![format!("{}{}{}", conf.url, "/api/v1/accounts/", uid)])
+    }
+
+    // fetch resolves account, if needed, then gets the user and its recent statuses
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let headers = conf.get_headers()?;
+            let url = |id: &str| format!("{}{}{}", conf.url, "/api/v1/accounts/", id);
+
+            let mut id = account::user_id(fetcher, conf, &headers).await?;
+            let mut user = fetcher.get_json(&url(&id), &headers).await;
+
+            // a cached id can be stale, resolve account again
+            if let (Err(GetError::StatusError(404)), None) = (&user, conf.get_user_id()) {
+                warn!(
+                    "Account id {} not found on {}, resolving again",
+                    id, conf.url
+                );
+                account::forget(conf);
+
+                id = account::user_id(fetcher, conf, &headers).await?;
+                user = fetcher.get_json(&url(&id), &headers).await;
+            }
+
+            // a migrated account still exists, follow it to its new account
+            let moved = match &user {
+                Ok(u) => u["moved"]["id"].as_str().map(String::from),
+                Err(_) => None,
+            };
+            if let Some(new_id) = moved {
+                warn!(
+                    "Account id {} moved to id {} on {}, following it",
+                    id, new_id, conf.url
+                );
+                if conf.get_user_id().is_none() {
+                    account::remember(conf, &new_id);
+                }
+
+                id = new_id;
+                user = fetcher.get_json(&url(&id), &headers).await;
+            }
+
+            let mut data = vec![user?];
+
+            // boosts are excluded, their counters are about someone else
+            if let Some(n) = conf.get_recent_statuses() {
+                let statuses = format!("{}/statuses?limit={}&exclude_reblogs=true", url(&id), n);
+                data.push(fetcher.get_json(&statuses, &headers).await?);
+            }
+
+            Ok(data)
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        let mut measurement = new_from_user(&data[0], conf)?;
+
+        if let Some(statuses) = data.get(1) {
+            add_statuses(&mut measurement, statuses, SystemTime::now(), conf)?;
+        }
+
+        Ok(vec![measurement])
+    }
+}
+
+// Implements for Activity
+impl Source for Activity {
+    fn kind(&self) -> &'static str {
+        "mastodon_activity"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api/v1/instance/activity")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        new_from_activity(&data[0], conf)
+    }
+}
+
+// Functions - private
+// new_from_activity will take weekly activity of a mastodon instance and convert it into Measurements,
+// timestamped at the start of each week so that a new run overwrites the same points
+fn new_from_activity(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Vec<Measurement>, TranslateError> {
+    let weeks = match val.as_array() {
+        Some(weeks) => weeks,
+        None => return Err(TranslateError::new("activity", conf)),
+    };
+
+    let mut measurements = Vec::new();
+    for week in weeks {
+        let mut measurement = Measurement::new(conf);
+
+        match as_i64(&week["week"]) {
+            Some(ts) if ts >= 0 => measurement.timestamp = Some(ts as u64),
+            _ => return Err(TranslateError::new("week", conf)),
+        }
+
+        // add fields
+        measurement.add_int("statuses", as_i64(&week["statuses"]), "statuses", conf)?;
+        measurement.add_int("logins", as_i64(&week["logins"]), "logins", conf)?;
+        measurement.add_int(
+            "registrations",
+            as_i64(&week["registrations"]),
+            "registrations",
+            conf,
+        )?;
+
+        measurements.push(measurement);
+    }
+
+    Ok(measurements)
+}
+
+// new_from_instance will take data from mastodon instance and convert it into a Measurement,
+// missing fields listed in optional are skipped
+fn new_from_instance(
+    val: &serde_json::Value,
+    optional: &[&str],
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields, as name, value and description
+    let fields = [
+        ("users", val["stats"]["user_count"].as_i64(), "user count"),
+        (
+            "local_posts",
+            val["stats"]["domain_count"].as_i64(),
+            "domain count",
+        ),
+        (
+            "posts",
+            val["stats"]["status_count"].as_i64(),
+            "status count",
+        ),
+    ];
+
+    for (name, value, desc) in fields.iter() {
+        if optional.contains(name) {
+            measurement.add_opt_int(name, *value);
+        } else {
+            measurement.add_int(name, *value, desc, conf)?;
+        }
+    }
+
+    // version
+    measurement.add_str("version", val["version"].as_str(), "version", conf)?;
+
+    Ok(measurement)
+}
+
+// new_from_user will take data from a mastodon user and convert it into a Measurement
+fn new_from_user(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    // url is not used as a tag for users
+    let mut measurement = Measurement {
+        key: conf.kind.clone(),
+        ..Default::default()
+    };
+    measurement
+        .tags
+        .insert("name".to_string(), conf.name.clone());
+
+    // add fields
+    // followers
+    measurement.add_int(
+        "followers",
+        val["followers_count"].as_i64(),
+        "followers",
+        conf,
+    )?;
+
+    // following
+    let following: i64 = val["following_count"].as_i64().unwrap_or(0);
+    measurement
+        .fields
+        .insert("following".to_string(), DataField::Int(following));
+
+    // posts
+    measurement.add_int(
+        "statuses",
+        val["statuses_count"].as_i64(),
+        "statuses count",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
+
+// Functions - public
+// as_i64 reads an int, mastodon sends activity and history counters as strings
+pub fn as_i64(val: &serde_json::Value) -> Option<i64> {
+    val.as_i64()
+        .or_else(|| val.as_str().and_then(|s| s.parse().ok()))
+}
+
+// add_statuses will take recent statuses (of a user or a tag) and add engagement fields to measurement
+pub fn add_statuses(
+    measurement: &mut Measurement,
+    val: &serde_json::Value,
+    now: SystemTime,
+    conf: &Config,
+) -> Result<(), TranslateError> {
+    let statuses = match val.as_array() {
+        Some(statuses) => statuses,
+        None => return Err(TranslateError::new("statuses", conf)),
+    };
+
+    let sum = |key: &str| statuses.iter().filter_map(|s| s[key].as_i64()).sum();
+
+    // add fields
+    // engagement
+    measurement.add_int(
+        "recent_statuses",
+        Some(statuses.len() as i64),
+        "recent statuses",
+        conf,
+    )?;
+    measurement.add_int(
+        "favourites",
+        Some(sum("favourites_count")),
+        "favourites",
+        conf,
+    )?;
+    measurement.add_int("reblogs", Some(sum("reblogs_count")), "reblogs", conf)?;
+    measurement.add_int("replies", Some(sum("replies_count")), "replies", conf)?;
+
+    // age of the latest status, in seconds, nothing if there is no status
+    let latest = statuses
+        .iter()
+        .filter_map(|s| s["created_at"].as_str())
+        .filter_map(|d| humantime::parse_rfc3339_weak(d).ok())
+        .max();
+    measurement.add_opt_int(
+        "last_status_age",
+        latest.map(|l| now.duration_since(l).unwrap_or_default().as_secs() as i64),
+    );
+
+    Ok(())
+}
+
+// validate_recent_statuses checks recent_statuses option, if set, can be sent to the api
+pub fn validate_recent_statuses(conf: &Config) -> Result<(), ConfigError> {
+    match conf.get_recent_statuses() {
+        Some(n) if n == 0 || n > MAX_STATUSES => {
+            error!(
+                "recent_statuses must be between 1 and {} in config {}",
+                MAX_STATUSES, conf.name
+            );
+            Err(ConfigError::InvalidOptionError(
+                "recent_statuses".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use std::fs::File;
+
+    #[test]
+    fn test_urls() {
+        // prepare
+        let conf = create_test_config();
+        let instance = Instance {
+            kind: "mastodon",
+            optional: &[],
+        };
+        let user = User {
+            kind: "mastodon_user",
+        };
+
+        // launch tests and check results
+        match instance.urls(&conf) {
+            Some(urls) => assert_eq!(urls, vec!["https://rage.love/api/v1/instance"]),
+            None => panic!("Error in urls test"),
+        };
+
+        // no user id
+        assert!(user.urls(&conf).is_none());
+    }
+
+    #[test]
+    fn test_new_from_mastodon() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let mesurement = new_from_instance(&json, &[], &conf).unwrap();
+
+        assert_eq!(mesurement.fields["users"], DataField::Int(31));
+        assert_eq!(mesurement.fields["posts"], DataField::Int(28354));
+    }
+
+    #[test]
+    fn test_new_from_pleroma() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.pleroma.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let mesurement = new_from_instance(&json, &[], &conf).unwrap();
+
+        assert_eq!(mesurement.fields["users"], DataField::Int(132));
+        assert_eq!(mesurement.fields["posts"], DataField::Int(30687));
+    }
+
+    #[test]
+    fn test_new_from_akkoma() {
+        // prepare
+        let conf = create_test_config();
+
+        let file =
+            File::open("./tests/json/test.new.from.akkoma.json").expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test, domain count is missing
+        assert!(new_from_instance(&json, &[], &conf).is_err());
+
+        let mesurement = new_from_instance(&json, &["local_posts"], &conf).unwrap();
+
+        assert_eq!(mesurement.fields["users"], DataField::Int(87));
+        assert_eq!(mesurement.fields["posts"], DataField::Int(48213));
+        assert!(!mesurement.fields.contains_key("local_posts"));
+    }
+
+    #[test]
+    fn test_new_from_gotosocial() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.gotosocial.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let mesurement = new_from_instance(&json, &["local_posts", "posts"], &conf).unwrap();
+
+        assert_eq!(mesurement.fields["users"], DataField::Int(12));
+        assert_eq!(
+            mesurement.fields["version"],
+            DataField::Str("0.13.0+git-8ea4c4b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_from_activity() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_activity.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurements = new_from_activity(&json, &conf).unwrap();
+
+        assert_eq!(measurements.len(), 12);
+        assert_eq!(measurements[0].timestamp, Some(1589155200));
+        assert_eq!(measurements[0].fields["statuses"], DataField::Int(147));
+        assert_eq!(measurements[11].timestamp, Some(1582502400));
+        assert_eq!(measurements[11].fields["logins"], DataField::Int(19));
+    }
+
+    #[test]
+    fn test_new_from_mastodon_user() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_user.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let mesurement = new_from_user(&json, &conf).unwrap();
+
+        assert_eq!(mesurement.fields["followers"], DataField::Int(274));
+        assert_eq!(mesurement.fields["statuses"], DataField::Int(15392));
+    }
+
+    #[test]
+    fn test_add_statuses() {
+        // prepare
+        let conf = create_test_config();
+        let mut measurement = Measurement::new(&conf);
+        let now = humantime::parse_rfc3339("2020-05-11T18:42:11Z").unwrap();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_user.statuses.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        add_statuses(&mut measurement, &json, now, &conf).unwrap();
+
+        assert_eq!(measurement.fields["recent_statuses"], DataField::Int(3));
+        assert_eq!(measurement.fields["favourites"], DataField::Int(17));
+        assert_eq!(measurement.fields["reblogs"], DataField::Int(4));
+        assert_eq!(measurement.fields["replies"], DataField::Int(3));
+        assert_eq!(measurement.fields["last_status_age"], DataField::Int(86400));
+
+        // no statuses, no age
+        let mut measurement = Measurement::new(&conf);
+        add_statuses(&mut measurement, &serde_json::json!([]), now, &conf).unwrap();
+        assert!(!measurement.fields.contains_key("last_status_age"));
+    }
+
+    #[test]
+    fn test_new_from_pleroma_user() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.pleroma_user.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let mesurement = new_from_user(&json, &conf).unwrap();
+
+        assert_eq!(mesurement.fields["followers"], DataField::Int(7));
+        assert_eq!(mesurement.fields["statuses"], DataField::Int(42));
+    }
+}
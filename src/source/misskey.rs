@@ -0,0 +1,265 @@
+// Mod misskey - sources for Misskey API compatible platforms (Misskey, Firefish, Sharkey)
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::json;
+
+// Structs - public
+// Instance collects metrics about an instance
+pub struct Instance;
+
+// User collects metrics about a specific user on an instance
+pub struct User;
+
+// Implements for Instance
+impl Source for Instance {
+    fn kind(&self) -> &'static str {
+        "misskey"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![
+            format!("{}{}", conf.url, "/api/stats"),
+            format!("{}{}", conf.url, "/api/meta"),
+        ])
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        // stats needs no parameter, meta without details is enough to get version
+        post_all(
+            fetcher,
+            conf,
+            self.urls(conf),
+            vec![json!({}), json!({ "detail": false })],
+        )
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_instance(&data[0], &data[1], conf)?])
+    }
+}
+
+// Implements for User
+impl Source for User {
+    fn kind(&self) -> &'static str {
+        "misskey_user"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        match conf.get_user_id() {
+            Some(_) => Ok(()),
+            None => {
+                error!("user_id option is missing in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("user_id".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_user_id()
+            .map(|_| vec![format!("{}{}", conf.url, "/api/users/show")])
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        post_all(
+            fetcher,
+            conf,
+            self.urls(conf),
+            vec![json!({ "userId": conf.get_user_id() })],
+        )
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_user(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// post_all posts each body to the matching url, in order, with headers of conf
+async fn post_all(
+    fetcher: &Fetcher,
+    conf: &Config,
+    urls: Option<Vec<String>>,
+    bodies: Vec<serde_json::Value>,
+) -> Result<Vec<serde_json::Value>, GetError> {
+    let urls = match urls {
+        Some(urls) => urls,
+        None => {
+            error!("Error forging url for misskey");
+            return Err(GetError::ForgeError);
+        }
+    };
+
+    let headers = conf.get_headers()?;
+
+    let mut data = Vec::new();
+    for (url, body) in urls.iter().zip(bodies.iter()) {
+        data.push(fetcher.post_json(url, body, &headers).await?);
+    }
+
+    Ok(data)
+}
+
+// new_from_instance will take stats and meta from a misskey instance and convert it into a Measurement
+fn new_from_instance(
+    stats: &serde_json::Value,
+    meta: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // notes
+    measurement.add_int("notes", stats["notesCount"].as_i64(), "notes count", conf)?;
+
+    // original notes
+    measurement.add_int(
+        "original_notes",
+        stats["originalNotesCount"].as_i64(),
+        "original notes count",
+        conf,
+    )?;
+
+    // users
+    measurement.add_int("users", stats["usersCount"].as_i64(), "users count", conf)?;
+
+    // original users
+    measurement.add_int(
+        "original_users",
+        stats["originalUsersCount"].as_i64(),
+        "original users count",
+        conf,
+    )?;
+
+    // instances
+    measurement.add_int("instances", stats["instances"].as_i64(), "instances", conf)?;
+
+    // drive usage, in bytes
+    measurement.add_int(
+        "drive_usage_local",
+        stats["driveUsageLocal"].as_i64(),
+        "drive usage local",
+        conf,
+    )?;
+    measurement.add_int(
+        "drive_usage_remote",
+        stats["driveUsageRemote"].as_i64(),
+        "drive usage remote",
+        conf,
+    )?;
+
+    // version
+    measurement.add_str("version", meta["version"].as_str(), "version", conf)?;
+
+    Ok(measurement)
+}
+
+// new_from_user will take data from a misskey user and convert it into a Measurement
+fn new_from_user(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    // url is not used as a tag for users
+    let mut measurement = Measurement {
+        key: conf.kind.clone(),
+        ..Default::default()
+    };
+    measurement
+        .tags
+        .insert("name".to_string(), conf.name.clone());
+
+    // add fields
+    // followers
+    measurement.add_int(
+        "followers",
+        val["followersCount"].as_i64(),
+        "followers",
+        conf,
+    )?;
+
+    // following
+    measurement.add_int(
+        "following",
+        val["followingCount"].as_i64(),
+        "following",
+        conf,
+    )?;
+
+    // notes
+    measurement.add_int("notes", val["notesCount"].as_i64(), "notes count", conf)?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_misskey() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.misskey.stats.json")
+            .expect("Unable to read test file");
+        let stats = serde_json::from_reader(file).expect("Error parsing json file");
+
+        let file = File::open("./tests/json/test.new.from.misskey.meta.json")
+            .expect("Unable to read test file");
+        let meta = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_instance(&stats, &meta, &conf).unwrap();
+
+        assert_eq!(measurement.fields["notes"], DataField::Int(5829481));
+        assert_eq!(measurement.fields["original_users"], DataField::Int(2411));
+        assert_eq!(measurement.fields["instances"], DataField::Int(11203));
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("2023.12.2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_new_from_misskey_user() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.misskey_user.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_user(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["followers"], DataField::Int(812));
+        assert_eq!(measurement.fields["notes"], DataField::Int(20451));
+
+        // no user_id
+        assert!(User.validate(&conf).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Mod mobilizon - source for Mobilizon instances
+// Uses
+use crate::config::Config;
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::json;
+
+// Statics
+// statistics, as field name and key in statistics
+static STATISTICS: [(&str, &str); 9] = [
+    ("users", "numberOfUsers"),
+    ("events", "numberOfEvents"),
+    ("local_events", "numberOfLocalEvents"),
+    ("comments", "numberOfComments"),
+    ("local_comments", "numberOfLocalComments"),
+    ("groups", "numberOfGroups"),
+    ("local_groups", "numberOfLocalGroups"),
+    ("instance_followers", "numberOfInstanceFollowers"),
+    ("instance_following", "numberOfInstanceFollowings"),
+];
+
+// Structs - public
+// Mobilizon collects metrics about a Mobilizon instance
+pub struct Mobilizon;
+
+// Implements for Mobilizon
+impl Source for Mobilizon {
+    fn kind(&self) -> &'static str {
+        "mobilizon"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api")])
+    }
+
+    // fetch queries the GraphQL api for statistics and version
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let keys: Vec<&str> = STATISTICS.iter().map(|(_, key)| *key).collect();
+            let query = json!({
+                "query": format!("{{ statistics {{ {} }} config {{ version }} }}", keys.join(" "))
+            });
+
+            Ok(vec![
+                fetcher
+                    .post_json(
+                        &format!("{}{}", conf.url, "/api"),
+                        &query,
+                        &conf.get_headers()?,
+                    )
+                    .await?,
+            ])
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_mobilizon(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_mobilizon will take a GraphQL response from mobilizon instance and convert it into a Measurement
+fn new_from_mobilizon(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+    let statistics = &val["data"]["statistics"];
+
+    // add fields
+    for (name, key) in STATISTICS.iter() {
+        measurement.add_int(name, statistics[key].as_i64(), key, conf)?;
+    }
+
+    // version
+    measurement.add_str(
+        "version",
+        val["data"]["config"]["version"].as_str(),
+        "version",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_mobilizon() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mobilizon.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_mobilizon(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(3021));
+        assert_eq!(measurement.fields["local_events"], DataField::Int(1873));
+        assert_eq!(measurement.fields["local_groups"], DataField::Int(211));
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("4.0.2".to_string())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+// Mod source - used to collect measurements from the various platforms
+
+// Reexporting
+pub mod account;
+pub mod activitypub;
+pub mod admin;
+pub mod friendica;
+pub mod funkwhale;
+pub mod json;
+pub mod lemmy;
+pub mod mastodon;
+pub mod misskey;
+pub mod mobilizon;
+pub mod nodeinfo;
+pub mod peers;
+pub mod peertube;
+pub mod pixelfed;
+pub mod plume;
+pub mod trends;
+
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use futures::future::{BoxFuture, FutureExt};
+use std::vec::Vec;
+
+// Traits
+// Source knows how to get measurements for one kind of config
+pub trait Source: Sync {
+    // kind of config handled by this source
+    fn kind(&self) -> &'static str;
+
+    // validate checks config has all the options needed by this source
+    fn validate(&self, _conf: &Config) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    // urls forges all endpoints needed to collect a config
+    fn urls(&self, conf: &Config) -> Option<Vec<String>>;
+
+    // fetch gets remote data from all endpoints, in order
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            // forge urls
+            let urls = match self.urls(conf) {
+                Some(urls) => urls,
+                None => {
+                    error!("Error forging url for {}", conf.kind);
+                    return Err(GetError::ForgeError);
+                }
+            };
+
+            let headers = conf.get_headers()?;
+
+            let mut data = Vec::new();
+            for url in urls {
+                data.push(fetcher.get_json(&url, &headers).await?);
+            }
+
+            Ok(data)
+        }
+        .boxed()
+    }
+
+    // translate converts fetched data into measurements
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError>;
+}
+
+// Statics
+// registry of all supported sources
+static SOURCES: &[&dyn Source] = &[
+    &mastodon::Instance {
+        kind: "mastodon",
+        optional: &[],
+    },
+    &mastodon::User {
+        kind: "mastodon_user",
+    },
+    &mastodon::Activity,
+    &admin::Admin,
+    &trends::Hashtag,
+    &trends::Trends,
+    &peers::Peers {
+        kind: "mastodon_peers",
+    },
+    &peers::Peers {
+        kind: "pleroma_peers",
+    },
+    &mastodon::Instance {
+        kind: "pleroma",
+        optional: &[],
+    },
+    &mastodon::User {
+        kind: "pleroma_user",
+    },
+    &mastodon::Instance {
+        kind: "akkoma",
+        optional: &["local_posts"],
+    },
+    // stats may be hidden, see instance-stats-mode
+    &mastodon::Instance {
+        kind: "gotosocial",
+        optional: &["local_posts", "posts"],
+    },
+    &plume::Plume,
+    &funkwhale::Funkwhale,
+    &json::Json,
+    &nodeinfo::NodeInfo,
+    &nodeinfo::Fixed {
+        kind: "diaspora",
+        path: "/nodeinfo/2.0",
+    },
+    &nodeinfo::Fixed {
+        kind: "hubzilla",
+        path: "/nodeinfo/2.0",
+    },
+    &friendica::Friendica,
+    // BookWyrm and WriteFreely only expose public counters through NodeInfo
+    &nodeinfo::Fixed {
+        kind: "bookwyrm",
+        path: "/nodeinfo/2.0",
+    },
+    &nodeinfo::Fixed {
+        kind: "writefreely",
+        path: "/api/nodeinfo",
+    },
+    &mobilizon::Mobilizon,
+    &misskey::Instance,
+    &misskey::User,
+    &lemmy::Site,
+    &lemmy::Community,
+    &peertube::Instance,
+    &peertube::Channel,
+    &pixelfed::Instance,
+    &pixelfed::User,
+    &activitypub::Actor,
+];
+
+// Functions - public
+// find returns the source handling kind, if supported
+pub fn find(kind: &str) -> Option<&'static dyn Source> {
+    SOURCES.iter().find(|s| s.kind() == kind).copied()
+}
+
+// supported returns all supported kinds
+pub fn supported() -> Vec<&'static str> {
+    SOURCES.iter().map(|s| s.kind()).collect()
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        match find("mastodon") {
+            Some(source) => assert_eq!(source.kind(), "mastodon"),
+            None => panic!("mastodon kind should be supported"),
+        }
+
+        assert!(find("shit").is_none());
+    }
+
+    #[test]
+    fn test_supported() {
+        let kinds = supported();
+
+        // no duplicated kinds in registry
+        for kind in kinds.iter() {
+            assert_eq!(kinds.iter().filter(|k| k == &kind).count(), 1);
+        }
+    }
+}
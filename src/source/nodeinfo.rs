@@ -0,0 +1,254 @@
+// Mod nodeinfo - source for any software exposing NodeInfo
+// Uses
+use crate::config::Config;
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+
+// Statics
+// supported NodeInfo schemas, from lowest to highest
+static SCHEMAS: [&str; 2] = [
+    "http://nodeinfo.diaspora.software/ns/schema/2.0",
+    "http://nodeinfo.diaspora.software/ns/schema/2.1",
+];
+
+// Structs - public
+// NodeInfo collects metrics about an instance using NodeInfo discovery
+pub struct NodeInfo;
+
+// Fixed collects metrics about an instance exposing NodeInfo on a known path
+pub struct Fixed {
+    pub kind: &'static str,
+    pub path: &'static str,
+}
+
+// Implements for NodeInfo
+impl Source for NodeInfo {
+    fn kind(&self) -> &'static str {
+        "nodeinfo"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/.well-known/nodeinfo")])
+    }
+
+    // fetch follows the well-known document to the highest supported schema
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let headers = conf.get_headers()?;
+
+            let well_known = fetcher
+                .get_json(
+                    &format!("{}{}", conf.url, "/.well-known/nodeinfo"),
+                    &headers,
+                )
+                .await?;
+
+            let href = match find_href(&well_known) {
+                Some(href) => href,
+                None => {
+                    error!("No supported NodeInfo schema found for {}", conf.url);
+                    return Err(GetError::DiscoveryError);
+                }
+            };
+
+            // href can point to another server
+            let headers = conf.get_headers_for(&href)?;
+
+            Ok(vec![fetcher.get_json(&href, &headers).await?])
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_nodeinfo(&data[0], conf)?])
+    }
+}
+
+// Implements for Fixed
+impl Source for Fixed {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, self.path)])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_nodeinfo(&data[0], conf)?])
+    }
+}
+
+// Functions - public
+// find_href returns the link to the highest supported schema found in the well-known document
+pub fn find_href(val: &serde_json::Value) -> Option<String> {
+    val["links"]
+        .as_array()?
+        .iter()
+        .filter_map(|link| {
+            let rank = SCHEMAS
+                .iter()
+                .position(|s| Some(*s) == link["rel"].as_str())?;
+            link["href"].as_str().map(|href| (rank, href))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, href)| href.to_string())
+}
+
+// Functions - private
+
+// new_from_nodeinfo will take a NodeInfo document and convert it into a Measurement
+fn new_from_nodeinfo(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // users
+    measurement.add_int(
+        "users",
+        val["usage"]["users"]["total"].as_i64(),
+        "users total",
+        conf,
+    )?;
+
+    // optional usage fields
+    measurement.add_opt_int(
+        "active_month",
+        val["usage"]["users"]["activeMonth"].as_i64(),
+    );
+    measurement.add_opt_int(
+        "active_halfyear",
+        val["usage"]["users"]["activeHalfyear"].as_i64(),
+    );
+    measurement.add_opt_int("local_posts", val["usage"]["localPosts"].as_i64());
+    measurement.add_opt_int("local_comments", val["usage"]["localComments"].as_i64());
+
+    // open registrations, as 0 or 1
+    measurement.add_int(
+        "open_registrations",
+        val["openRegistrations"].as_bool().map(i64::from),
+        "open registrations",
+        conf,
+    )?;
+
+    // software
+    measurement.add_str(
+        "software",
+        val["software"]["name"].as_str(),
+        "software name",
+        conf,
+    )?;
+    measurement.add_str(
+        "version",
+        val["software"]["version"].as_str(),
+        "version",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_find_href() {
+        // prepare
+        let file = File::open("./tests/json/test.nodeinfo.well-known.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test, 2.1 is preferred over 2.0
+        assert_eq!(find_href(&json).unwrap(), "https://rage.love/nodeinfo/2.1");
+
+        // unknown schemas only
+        let json = serde_json::json!({
+            "links": [{"rel": "http://nodeinfo.diaspora.software/ns/schema/1.0", "href": "https://rage.love/nodeinfo/1.0"}]
+        });
+        assert!(find_href(&json).is_none());
+    }
+
+    #[test]
+    fn test_new_from_nodeinfo() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.nodeinfo.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_nodeinfo(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(1712));
+        assert_eq!(measurement.fields["active_month"], DataField::Int(418));
+        assert_eq!(measurement.fields["local_posts"], DataField::Int(286451));
+        assert_eq!(measurement.fields["open_registrations"], DataField::Int(1));
+        assert_eq!(
+            measurement.fields["software"],
+            DataField::Str("mastodon".to_string())
+        );
+        assert!(!measurement.fields.contains_key("local_comments"));
+    }
+
+    #[test]
+    fn test_fixed() {
+        // prepare
+        let conf = create_test_config();
+        let diaspora = Fixed {
+            kind: "diaspora",
+            path: "/nodeinfo/2.0",
+        };
+
+        let file = File::open("./tests/json/test.new.from.diaspora.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        assert_eq!(
+            diaspora.urls(&conf).unwrap(),
+            vec!["https://rage.love/nodeinfo/2.0"]
+        );
+
+        let measurements = diaspora.translate(&[json], &conf).unwrap();
+
+        assert_eq!(measurements[0].fields["users"], DataField::Int(2891));
+        assert_eq!(
+            measurements[0].fields["local_posts"],
+            DataField::Int(412099)
+        );
+        assert_eq!(
+            measurements[0].fields["local_comments"],
+            DataField::Int(901233)
+        );
+        assert_eq!(
+            measurements[0].fields["version"],
+            DataField::Str("0.7.18.2".to_string())
+        );
+    }
+}
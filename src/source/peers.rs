@@ -0,0 +1,169 @@
+// Mod peers - federation peers of Mastodon API compatible platforms
+// Uses
+use crate::config::Config;
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use std::collections::BTreeSet;
+use std::{fs, io};
+
+// Structs - public
+// Peers collects the number of instances an instance federates with
+pub struct Peers {
+    pub kind: &'static str,
+}
+
+// Implements for Peers
+impl Source for Peers {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api/v1/instance/peers")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_peers(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// read_peers reads known peers from path, None if there is no previous run
+fn read_peers(path: &str) -> io::Result<Option<BTreeSet<String>>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(
+            content
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(String::from)
+                .collect(),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// write_peers writes peers to path, one per line
+fn write_peers(path: &str, peers: &BTreeSet<String>) -> io::Result<()> {
+    let mut content = String::new();
+    for peer in peers {
+        content.push_str(peer);
+        content.push('\n');
+    }
+
+    // write then rename, to never leave a partial file behind
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, content)?;
+    fs::rename(tmp, path)
+}
+
+// new_from_peers will take peers of an instance and convert it into a Measurement,
+// if a peers file is set, new and gone peers since last run are also recorded
+fn new_from_peers(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    let peers: BTreeSet<String> = match val.as_array() {
+        Some(peers) => peers
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(String::from)
+            .collect(),
+        None => return Err(TranslateError::new("peers", conf)),
+    };
+
+    // add fields
+    // peers
+    measurement.add_int("peers", Some(peers.len() as i64), "peers", conf)?;
+
+    // new and gone peers
+    if let Some(path) = conf.get_peers_file() {
+        match read_peers(&path) {
+            // nothing to compare with on first run
+            Ok(None) => (),
+            Ok(Some(known)) => {
+                let new = peers.difference(&known).count() as i64;
+                let gone = known.difference(&peers).count() as i64;
+
+                measurement.add_int("new_peers", Some(new), "new peers", conf)?;
+                measurement.add_int("gone_peers", Some(gone), "gone peers", conf)?;
+            }
+            Err(e) => warn!("Error reading peers file {} : {}", path, e),
+        }
+
+        if let Err(e) = write_peers(&path, &peers) {
+            warn!("Error writing peers file {} : {}", path, e);
+        }
+    }
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_peers() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.mastodon_peers.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_peers(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["peers"], DataField::Int(6));
+        assert!(!measurement.fields.contains_key("new_peers"));
+    }
+
+    #[test]
+    fn test_new_from_peers_with_file() {
+        // prepare
+        let path = std::env::temp_dir().join(format!("fediwatcher.peers.{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&path);
+
+        let conf: Config = toml::from_str(&format!(
+            r#"
+            name = "rage.love"
+            url = "https://rage.love"
+            kind = "mastodon_peers"
+
+            [options]
+            peers_file = {:?}
+            "#,
+            path
+        ))
+        .expect("Error parsing test config");
+
+        // launch test
+        // first run, no previous peers
+        let first = serde_json::json!(["a.social", "b.social", "c.social"]);
+        let measurement = new_from_peers(&first, &conf).unwrap();
+        assert!(!measurement.fields.contains_key("new_peers"));
+
+        // second run, one new and two gone
+        let second = serde_json::json!(["a.social", "d.social"]);
+        let measurement = new_from_peers(&second, &conf).unwrap();
+        assert_eq!(measurement.fields["peers"], DataField::Int(2));
+        assert_eq!(measurement.fields["new_peers"], DataField::Int(1));
+        assert_eq!(measurement.fields["gone_peers"], DataField::Int(2));
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a.social\nd.social\n");
+
+        // cleanup
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+// Mod peertube - sources for PeerTube instances
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Statics
+// instance stats, as field name and key in server stats
+static STATS: [(&str, &str); 11] = [
+    ("users", "totalUsers"),
+    ("active_users_day", "totalDailyActiveUsers"),
+    ("active_users_week", "totalWeeklyActiveUsers"),
+    ("active_users_month", "totalMonthlyActiveUsers"),
+    ("local_videos", "totalLocalVideos"),
+    ("local_video_views", "totalLocalVideoViews"),
+    ("local_video_comments", "totalLocalVideoComments"),
+    ("local_video_files_size", "totalLocalVideoFilesSize"),
+    ("video_comments", "totalVideoComments"),
+    ("instance_followers", "totalInstanceFollowers"),
+    ("instance_following", "totalInstanceFollowing"),
+];
+
+// Structs - public
+// Instance collects metrics about a PeerTube instance
+pub struct Instance;
+
+// Channel collects metrics about a specific video channel on an instance
+pub struct Channel;
+
+// Implements for Instance
+impl Source for Instance {
+    fn kind(&self) -> &'static str {
+        "peertube"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/api/v1/server/stats")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_instance(&data[0], conf)?])
+    }
+}
+
+// Implements for Channel
+impl Source for Channel {
+    fn kind(&self) -> &'static str {
+        "peertube_channel"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        match conf.get_channel() {
+            Some(_) => Ok(()),
+            None => {
+                error!("channel option is missing in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("channel".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_channel().map(|channel| {
+            vec![
+                format!("{}{}{}", conf.url, "/api/v1/video-channels/", channel),
+                // only the total count of videos is needed
+                format!(
+                    "{}{}{}{}",
+                    conf.url, "/api/v1/video-channels/", channel, "/videos?count=0"
+                ),
+            ]
+        })
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_channel(&data[0], &data[1], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_instance will take stats from a peertube instance and convert it into a Measurement
+fn new_from_instance(
+    val: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    for (name, key) in STATS.iter() {
+        measurement.add_int(name, val[key].as_i64(), key, conf)?;
+    }
+
+    Ok(measurement)
+}
+
+// new_from_channel will take a peertube channel and its videos and convert it into a Measurement
+fn new_from_channel(
+    channel: &serde_json::Value,
+    videos: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // followers
+    measurement.add_int(
+        "followers",
+        channel["followersCount"].as_i64(),
+        "followers count",
+        conf,
+    )?;
+
+    // videos
+    measurement.add_int("videos", videos["total"].as_i64(), "videos total", conf)?;
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_peertube() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.peertube.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_instance(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(1392));
+        assert_eq!(measurement.fields["local_videos"], DataField::Int(8214));
+        assert_eq!(
+            measurement.fields["local_video_files_size"],
+            DataField::Int(3298534883328)
+        );
+        assert_eq!(
+            measurement.fields["active_users_month"],
+            DataField::Int(201)
+        );
+    }
+
+    #[test]
+    fn test_new_from_peertube_channel() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.peertube_channel.json")
+            .expect("Unable to read test file");
+
+        let channel = serde_json::from_reader(file).expect("Error parsing json file");
+        let videos = serde_json::json!({ "total": 57, "data": [] });
+
+        // launch test
+        let measurement = new_from_channel(&channel, &videos, &conf).unwrap();
+
+        assert_eq!(measurement.fields["followers"], DataField::Int(2319));
+        assert_eq!(measurement.fields["videos"], DataField::Int(57));
+    }
+}
@@ -0,0 +1,239 @@
+// Mod pixelfed - sources for Pixelfed instances
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+
+// Structs - public
+// Instance collects metrics about a Pixelfed instance
+pub struct Instance;
+
+// User collects metrics about a specific user on an instance
+pub struct User;
+
+// Implements for Instance
+impl Source for Instance {
+    fn kind(&self) -> &'static str {
+        "pixelfed"
+    }
+
+    // Pixelfed does not always fill stats in the Mastodon compatible api,
+    // NodeInfo is used as a fallback
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![
+            format!("{}{}", conf.url, "/api/v1/instance"),
+            format!("{}{}", conf.url, "/api/nodeinfo/2.0.json"),
+        ])
+    }
+
+    // fetch gets the instance, then NodeInfo if the instance serves it
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let headers = conf.get_headers()?;
+            let urls = self.urls(conf).ok_or(GetError::ForgeError)?;
+
+            let instance = fetcher.get_json(&urls[0], &headers).await?;
+
+            // NodeInfo is only a fallback, an instance without it is not an error
+            let nodeinfo = match fetcher.get_json(&urls[1], &headers).await {
+                Ok(nodeinfo) => nodeinfo,
+                Err(e) => {
+                    debug!("Error fetching NodeInfo of {} : {:?}", conf.url, e);
+                    serde_json::Value::Null
+                }
+            };
+
+            Ok(vec![instance, nodeinfo])
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_instance(&data[0], &data[1], conf)?])
+    }
+}
+
+// Implements for User
+impl Source for User {
+    fn kind(&self) -> &'static str {
+        "pixelfed_user"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        match conf.get_user_id() {
+            Some(_) => Ok(()),
+            None => {
+                error!("user_id option is missing in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("user_id".to_string()))
+            }
+        }
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        conf.get_user_id()
+            .map(|uid| vec![format!("{}{}{}", conf.url, "/api/v1/accounts/", uid)])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_user(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_instance will take data from pixelfed instance and its nodeinfo and convert it into a Measurement
+fn new_from_instance(
+    instance: &serde_json::Value,
+    nodeinfo: &serde_json::Value,
+    conf: &Config,
+) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // users
+    measurement.add_int(
+        "users",
+        instance["stats"]["user_count"]
+            .as_i64()
+            .or_else(|| nodeinfo["usage"]["users"]["total"].as_i64()),
+        "user count",
+        conf,
+    )?;
+
+    // posts
+    measurement.add_opt_int(
+        "posts",
+        instance["stats"]["status_count"]
+            .as_i64()
+            .or_else(|| nodeinfo["usage"]["localPosts"].as_i64()),
+    );
+
+    // domains
+    measurement.add_opt_int("domains", instance["stats"]["domain_count"].as_i64());
+
+    // version, the one in instance is the Mastodon api version
+    measurement.add_str(
+        "version",
+        nodeinfo["software"]["version"]
+            .as_str()
+            .or_else(|| instance["version"].as_str()),
+        "version",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
+
+// new_from_user will take data from a pixelfed user and convert it into a Measurement
+fn new_from_user(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    // url is not used as a tag for users
+    let mut measurement = Measurement {
+        key: conf.kind.clone(),
+        ..Default::default()
+    };
+    measurement
+        .tags
+        .insert("name".to_string(), conf.name.clone());
+
+    // add fields
+    // followers
+    measurement.add_int(
+        "followers",
+        val["followers_count"].as_i64(),
+        "followers",
+        conf,
+    )?;
+
+    // following and statuses are hidden by some instances
+    measurement.add_opt_int("following", val["following_count"].as_i64());
+    measurement.add_opt_int("statuses", val["statuses_count"].as_i64());
+
+    Ok(measurement)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_new_from_pixelfed() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.pixelfed.json")
+            .expect("Unable to read test file");
+        let instance = serde_json::from_reader(file).expect("Error parsing json file");
+
+        let file = File::open("./tests/json/test.new.from.pixelfed.nodeinfo.json")
+            .expect("Unable to read test file");
+        let nodeinfo = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_instance(&instance, &nodeinfo, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(4133));
+        assert_eq!(measurement.fields["posts"], DataField::Int(129044));
+        assert_eq!(
+            measurement.fields["version"],
+            DataField::Str("0.11.9".to_string())
+        );
+
+        // stats missing from instance, nodeinfo is used
+        let instance = serde_json::json!({ "version": "3.5.3 (compatible; Pixelfed 0.11.9)" });
+        let measurement = new_from_instance(&instance, &nodeinfo, &conf).unwrap();
+
+        assert_eq!(measurement.fields["users"], DataField::Int(4133));
+        assert_eq!(measurement.fields["posts"], DataField::Int(129044));
+        assert!(!measurement.fields.contains_key("domains"));
+
+        // no nodeinfo, instance is enough
+        let file = File::open("./tests/json/test.new.from.pixelfed.json")
+            .expect("Unable to read test file");
+        let instance = serde_json::from_reader(file).expect("Error parsing json file");
+        let measurement = Instance
+            .translate(&[instance, serde_json::Value::Null], &conf)
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(measurement.fields["users"], DataField::Int(4133));
+        assert!(measurement.fields.contains_key("version"));
+    }
+
+    #[test]
+    fn test_new_from_pixelfed_user() {
+        // prepare
+        let conf = create_test_config();
+
+        let file = File::open("./tests/json/test.new.from.pixelfed_user.json")
+            .expect("Unable to read test file");
+
+        let json = serde_json::from_reader(file).expect("Error parsing json file");
+
+        // launch test
+        let measurement = new_from_user(&json, &conf).unwrap();
+
+        assert_eq!(measurement.fields["followers"], DataField::Int(96));
+        assert_eq!(measurement.fields["statuses"], DataField::Int(311));
+
+        // no user_id
+        assert!(User.validate(&conf).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+// Mod plume - source for Plume instances
+// Uses
+use crate::config::Config;
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::Source;
+
+// Structs - public
+// Plume collects metrics about a Plume instance
+pub struct Plume;
+
+// Implements for Plume
+impl Source for Plume {
+    fn kind(&self) -> &'static str {
+        "plume"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![format!("{}{}", conf.url, "/nodeinfo/2.0")])
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        Ok(vec![new_from_plume(&data[0], conf)?])
+    }
+}
+
+// Functions - private
+// new_from_plume will take data from plume instance and convert it into a Measurement
+fn new_from_plume(val: &serde_json::Value, conf: &Config) -> Result<Measurement, TranslateError> {
+    let mut measurement = Measurement::new(conf);
+
+    // add fields
+    // user_count
+    measurement.add_int(
+        "users",
+        val["usage"]["users"]["total"].as_i64(),
+        "users total",
+        conf,
+    )?;
+
+    // local_posts
+    measurement.add_int(
+        "local_posts",
+        val["usage"]["localPosts"].as_i64(),
+        "local posts",
+        conf,
+    )?;
+
+    // comments
+    measurement.add_int(
+        "local_comments",
+        val["usage"]["localComments"].as_i64(),
+        "local comments",
+        conf,
+    )?;
+
+    // version
+    measurement.add_str(
+        "version",
+        val["software"]["version"].as_str(),
+        "version",
+        conf,
+    )?;
+
+    Ok(measurement)
+}
@@ -0,0 +1,334 @@
+// Mod trends - sources for hashtags and trends of Mastodon instances
+// Uses
+use crate::config::{Config, ConfigError};
+use crate::get::{Fetcher, GetError};
+use crate::influx::translate::{Measurement, TranslateError};
+use crate::source::mastodon::{add_statuses, as_i64, validate_recent_statuses};
+use crate::source::Source;
+use futures::future::{BoxFuture, FutureExt};
+use std::time::SystemTime;
+
+// Structs - public
+// Hashtag collects daily uses of hashtags, and their recent statuses
+pub struct Hashtag;
+
+// Trends collects trending tags and links of an instance
+pub struct Trends;
+
+// Implements for Hashtag
+impl Source for Hashtag {
+    fn kind(&self) -> &'static str {
+        "mastodon_hashtag"
+    }
+
+    fn validate(&self, conf: &Config) -> Result<(), ConfigError> {
+        validate_recent_statuses(conf)?;
+
+        match conf.get_tags() {
+            Some(tags) if !tags.is_empty() && tags.iter().all(|t| !normalize(t).is_empty()) => {
+                Ok(())
+            }
+            _ => {
+                error!("tags option is missing or empty in config {}", conf.name);
+                Err(ConfigError::MissingOptionError("tags".to_string()))
+            }
+        }
+    }
+
+    // urls forges, for each tag, the tag and its timeline endpoints
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        let limit = conf
+            .get_recent_statuses()
+            .map(|n| format!("?limit={}", n))
+            .unwrap_or_default();
+
+        conf.get_tags().map(|tags| {
+            tags.iter()
+                .map(|t| normalize(t))
+                .flat_map(|t| {
+                    vec![
+                        format!("{}{}{}", conf.url, "/api/v1/tags/", t),
+                        format!("{}{}{}{}", conf.url, "/api/v1/timelines/tag/", t, limit),
+                    ]
+                })
+                .collect()
+        })
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        let tags = match conf.get_tags() {
+            Some(tags) => tags,
+            None => return Err(TranslateError::new("tags", conf)),
+        };
+
+        let now = SystemTime::now();
+        let mut measurements = Vec::new();
+        for (tag, pair) in tags.iter().zip(data.chunks(2)) {
+            if pair.len() < 2 {
+                return Err(TranslateError::new("timeline", conf));
+            }
+
+            measurements.append(&mut new_from_tag(
+                &pair[0],
+                &pair[1],
+                &normalize(tag),
+                now,
+                conf,
+            )?);
+        }
+
+        Ok(measurements)
+    }
+}
+
+// Implements for Trends
+impl Source for Trends {
+    fn kind(&self) -> &'static str {
+        "mastodon_trends"
+    }
+
+    fn urls(&self, conf: &Config) -> Option<Vec<String>> {
+        Some(vec![
+            format!("{}{}", conf.url, "/api/v1/trends"),
+            format!("{}{}", conf.url, "/api/v1/trends/links"),
+        ])
+    }
+
+    // fetch gets trending tags, then trending links if the instance has them
+    fn fetch<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+        conf: &'a Config,
+    ) -> BoxFuture<'a, Result<Vec<serde_json::Value>, GetError>> {
+        async move {
+            let headers = conf.get_headers()?;
+            let urls = self.urls(conf).ok_or(GetError::ForgeError)?;
+
+            let tags = fetcher.get_json(&urls[0], &headers).await?;
+
+            // trending links are not available before Mastodon 3.5
+            let links = match fetcher.get_json(&urls[1], &headers).await {
+                Err(GetError::StatusError(404)) => serde_json::Value::Null,
+                links => links?,
+            };
+
+            Ok(vec![tags, links])
+        }
+        .boxed()
+    }
+
+    fn translate(
+        &self,
+        data: &[serde_json::Value],
+        conf: &Config,
+    ) -> Result<Vec<Measurement>, TranslateError> {
+        let mut measurements = new_from_trends(&data[0], "tag", conf)?;
+
+        // no trending links before Mastodon 3.5
+        if !data[1].is_null() {
+            measurements.append(&mut new_from_trends(&data[1], "link", conf)?);
+        }
+
+        Ok(measurements)
+    }
+}
+
+// Functions - private
+// normalize returns a tag as used in urls and tags (eg: "#Rust" is "rust")
+fn normalize(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+// new_from_tag will take a tag and its timeline and convert them into Measurements,
+// one per day of history, timestamped at the start of the day, plus one for recent statuses
+fn new_from_tag(
+    val: &serde_json::Value,
+    timeline: &serde_json::Value,
+    tag: &str,
+    now: SystemTime,
+    conf: &Config,
+) -> Result<Vec<Measurement>, TranslateError> {
+    let days = match val["history"].as_array() {
+        Some(days) => days,
+        None => return Err(TranslateError::new("history", conf)),
+    };
+
+    let mut measurements = Vec::new();
+    for day in days {
+        let mut measurement = Measurement::new(conf);
+        measurement.tags.insert("tag".to_string(), tag.to_string());
+
+        match as_i64(&day["day"]) {
+            Some(ts) if ts >= 0 => measurement.timestamp = Some(ts as u64),
+            _ => return Err(TranslateError::new("day", conf)),
+        }
+
+        // add fields
+        measurement.add_int("uses", as_i64(&day["uses"]), "uses", conf)?;
+        measurement.add_int("accounts", as_i64(&day["accounts"]), "accounts", conf)?;
+
+        measurements.push(measurement);
+    }
+
+    // recent statuses
+    let mut measurement = Measurement::new(conf);
+    measurement.tags.insert("tag".to_string(), tag.to_string());
+    add_statuses(&mut measurement, timeline, now, conf)?;
+    measurements.push(measurement);
+
+    Ok(measurements)
+}
+
+// new_from_trends will take trending items and convert them into Measurements, one per item,
+// using the tag name or the link url as a tag called name
+fn new_from_trends(
+    val: &serde_json::Value,
+    name: &str,
+    conf: &Config,
+) -> Result<Vec<Measurement>, TranslateError> {
+    let items = match val.as_array() {
+        Some(items) => items,
+        None => return Err(TranslateError::new("trends", conf)),
+    };
+
+    let mut measurements = Vec::new();
+    for (rank, item) in items.iter().enumerate() {
+        let mut measurement = Measurement::new(conf);
+
+        // tags are identified by their name, links by their url
+        let id = match name {
+            "tag" => item["name"].as_str().map(normalize),
+            _ => {
+                measurement.key = format!("{}_{}s", conf.kind, name);
+                item["url"].as_str().map(String::from)
+            }
+        };
+        match id {
+            Some(id) => measurement.tags.insert(name.to_string(), id),
+            None => return Err(TranslateError::new(name, conf)),
+        };
+
+        // add fields
+        // rank, starting at 1
+        measurement.add_int("rank", Some(rank as i64 + 1), "rank", conf)?;
+
+        // today is the first day of history
+        let today = &item["history"][0];
+        measurement.add_int("uses", as_i64(&today["uses"]), "uses", conf)?;
+        measurement.add_int("accounts", as_i64(&today["accounts"]), "accounts", conf)?;
+
+        measurements.push(measurement);
+    }
+
+    Ok(measurements)
+}
+
+// Tests
+// Tester c'est douter
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_test_config;
+    use crate::influx::translate::DataField;
+    use std::fs::File;
+
+    #[test]
+    fn test_urls() {
+        // prepare
+        let conf: Config = toml::from_str(
+            r##"
+            name = "rage.love"
+            url = "https://rage.love"
+            kind = "mastodon_hashtag"
+
+            [options]
+            tags = ["#Rust", "fediverse"]
+            recent_statuses = 10
+            "##,
+        )
+        .expect("Error parsing test config");
+
+        // launch test
+        assert!(Hashtag.validate(&conf).is_ok());
+        assert_eq!(
+            Hashtag.urls(&conf).unwrap(),
+            vec![
+                "https://rage.love/api/v1/tags/rust",
+                "https://rage.love/api/v1/timelines/tag/rust?limit=10",
+                "https://rage.love/api/v1/tags/fediverse",
+                "https://rage.love/api/v1/timelines/tag/fediverse?limit=10",
+            ]
+        );
+
+        // no tags
+        assert!(Hashtag.validate(&create_test_config()).is_err());
+    }
+
+    #[test]
+    fn test_new_from_tag() {
+        // prepare
+        let conf = create_test_config();
+        let now = humantime::parse_rfc3339("2020-05-11T18:42:11Z").unwrap();
+
+        let tag = File::open("./tests/json/test.new.from.mastodon_hashtag.json")
+            .expect("Unable to read test file");
+        let tag = serde_json::from_reader(tag).expect("Error parsing json file");
+
+        let timeline = File::open("./tests/json/test.new.from.mastodon_user.statuses.json")
+            .expect("Unable to read test file");
+        let timeline = serde_json::from_reader(timeline).expect("Error parsing json file");
+
+        // launch test
+        let measurements = new_from_tag(&tag, &timeline, "rust", now, &conf).unwrap();
+
+        // 7 days and recent statuses
+        assert_eq!(measurements.len(), 8);
+        assert_eq!(measurements[0].tags["tag"], "rust");
+        assert_eq!(measurements[0].timestamp, Some(1589155200));
+        assert_eq!(measurements[0].fields["uses"], DataField::Int(34));
+        assert_eq!(measurements[0].fields["accounts"], DataField::Int(21));
+        assert_eq!(measurements[7].timestamp, None);
+        assert_eq!(measurements[7].fields["recent_statuses"], DataField::Int(3));
+    }
+
+    #[test]
+    fn test_new_from_trends() {
+        // prepare
+        let conf = create_test_config();
+
+        let tags = File::open("./tests/json/test.new.from.mastodon_trends.json")
+            .expect("Unable to read test file");
+        let tags = serde_json::from_reader(tags).expect("Error parsing json file");
+
+        let links = File::open("./tests/json/test.new.from.mastodon_trends.links.json")
+            .expect("Unable to read test file");
+        let links = serde_json::from_reader(links).expect("Error parsing json file");
+
+        // launch test
+        let measurements = Trends.translate(&[tags, links], &conf).unwrap();
+
+        assert_eq!(measurements.len(), 3);
+        assert_eq!(measurements[0].key, "mastodon");
+        assert_eq!(measurements[0].tags["tag"], "caturday");
+        assert_eq!(measurements[0].fields["rank"], DataField::Int(1));
+        assert_eq!(measurements[0].fields["uses"], DataField::Int(412));
+        assert_eq!(measurements[1].tags["tag"], "rustlang");
+        assert_eq!(measurements[2].key, "mastodon_links");
+        assert_eq!(
+            measurements[2].tags["link"],
+            "https://blog.rust-lang.org/2020/04/23/Rust-1.43.0.html"
+        );
+        assert_eq!(measurements[2].fields["accounts"], DataField::Int(87));
+
+        // no links on older instances
+        let old = serde_json::Value::Null;
+        let measurements = Trends
+            .translate(&[serde_json::json!([]), old], &conf)
+            .unwrap();
+        assert!(measurements.is_empty());
+    }
+}